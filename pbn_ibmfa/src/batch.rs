@@ -0,0 +1,267 @@
+use std::{fs, io::Read, path::{Path, PathBuf}, process, thread,
+    time::{Duration, Instant}};
+
+use json::{JsonValue, object};
+
+use crate::model_format::ModelFormat;
+
+
+/*******************************************************************************
+ * Batch analysis
+ * ==============
+ * The models of a directory (or of a pattern) are analysed one by one, each
+ * in a separate process of the command line tool, so a model running out
+ * of time or memory is killed and reported while the others continue.
+ * The runtime and the peak memory are measured for every model and
+ * the outputs are summarized.
+ ******************************************************************************/
+
+/// Analyses every model of `models` by `analysis` of the executable `exe`
+/// and summarizes them, a report per model. A failed analysis is reported
+/// by its `error`.
+///
+/// * `global_args` - Arguments given before the model, e.g. `-t 10`.
+/// * `analysis_args` - Arguments of `analysis`.
+/// * `timeout` - Kill the analysis of a model after the seconds.
+pub fn run_batch(
+    exe: &Path,
+    models: &[PathBuf],
+    global_args: &[String],
+    analysis_args: &[String],
+    timeout: Option<f64>,
+) -> JsonValue {
+    let reports = models.iter()
+        .map(|model| {
+            let mut command = process::Command::new(exe);
+            command.args(global_args)
+                .arg("--peak-memory")
+                .arg(model)
+                .arg("analysis")
+                .args(analysis_args);
+            let run = run_measured(command, timeout);
+            let mut report = object!{
+                model: model.display().to_string(),
+                seconds: run.seconds,
+                peak_memory_kb: run.peak_memory_kb,
+            };
+            let output = run.output.and_then(|output| json::parse(&output)
+                .map_err(|err| format!("Invalid output, err: {err}")));
+            match output {
+                Ok(output) => {
+                    report["variables"] = output["state_variables"].len()
+                        .into();
+                    report["colors"] = output["colors"].clone();
+                    report["attractors"] = output["attractors"].len().into();
+                    if output["attractors"].members()
+                            .any(|attr| attr.has_key("strong-driver-set")) {
+                        report["driver_set_sizes"] = output["attractors"]
+                            .members()
+                            .map(|attr| attr["strong-driver-set"].len())
+                            .collect::<Vec<_>>()
+                            .into();
+                    }
+                    report["error"] = JsonValue::Null;
+                },
+                Err(err) => report["error"] = err.into(),
+            }
+            report
+        })
+        .collect::<json::Array>();
+
+    object!{
+        models: reports.len(),
+        failures: reports.iter()
+            .filter(|report| !report["error"].is_null())
+            .count(),
+        reports: reports,
+    }
+}
+
+/// The files of models in the directory `path`, or the files matching
+/// the last component of `path` with wildcards, see `wildcard_match`.
+pub fn batch_models(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let (dir, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        let pattern = path.file_name().and_then(|name| name.to_str())
+            .ok_or(format!("Invalid models '{}'.", path.display()))?;
+        let dir = path.parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (dir, Some(pattern))
+    };
+    let entries = fs::read_dir(dir).map_err(|err| format!(
+        "Cannot read the directory '{}', err: {err}", dir.display()))?;
+    let mut models = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| match pattern {
+            Some(pattern) => path.file_name().and_then(|name| name.to_str())
+                .is_some_and(|name| wildcard_match(pattern, name)),
+            None => ModelFormat::from_path(path).is_some(),
+        })
+        .collect::<Vec<_>>();
+    models.sort();
+    if models.is_empty() {
+        return Err(format!("No models in '{}'.", path.display()));
+    }
+    Ok(models)
+}
+
+/// Whether the whole `name` matches `pattern`, `*` matches any string,
+/// `?` any character.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // matches[j] - the processed part of the pattern matches name[..j]
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+    for c in pattern {
+        matches = match c {
+            '*' => matches.iter()
+                .scan(false, |any, matched| {
+                    *any |= *matched;
+                    Some(*any)
+                })
+                .collect(),
+            _ => std::iter::once(false)
+                .chain((0..name.len()).map(|j|
+                    matches[j] && (c == '?' || c == name[j])))
+                .collect(),
+        };
+    }
+    matches[name.len()]
+}
+
+/// A finished run of a process.
+pub struct MeasuredRun {
+    /// The standard output, or the error of a failed run.
+    pub output: Result<String, String>,
+    pub seconds: f64,
+    /// The peak resident memory (only on Linux).
+    pub peak_memory_kb: Option<u64>,
+}
+
+/// Runs `command` to the end or until `timeout` seconds, measuring
+/// the time and the memory. A child reporting `Peak memory: {kB} kB`
+/// on the standard error (see `--peak-memory`) overrides the sampled peak.
+pub fn run_measured(mut command: process::Command, timeout: Option<f64>)
+-> MeasuredRun {
+    let start = Instant::now();
+    let child = command.stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return MeasuredRun {
+            output: Err(format!("Cannot run the analysis, err: {err}")),
+            seconds: 0.0,
+            peak_memory_kb: None,
+        },
+    };
+    // The pipes are read by threads, a full pipe would block the child
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let mut peak_memory_kb = None;
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(memory) = peak_memory(child.id()) {
+            peak_memory_kb = Some(memory);
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => {},
+            Err(err) => break Err(err),
+        }
+        if timeout.is_some_and(|timeout|
+                start.elapsed().as_secs_f64() > timeout) {
+            timed_out = true;
+            let _ = child.kill();
+            break child.wait();
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let seconds = start.elapsed().as_secs_f64();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    // The peak reported by the child itself, the samples miss short runs
+    if let Some(memory) = stderr.lines()
+            .find_map(|line| line.strip_prefix("Peak memory: ")?
+                .strip_suffix(" kB")?.parse().ok()) {
+        peak_memory_kb = Some(memory);
+    }
+
+    let output = match status {
+        _ if timed_out => Err(format!("Timeout after {seconds:.1} s")),
+        Ok(status) if status.success() => Ok(stdout),
+        Ok(status) => Err(match stderr.trim() {
+            "" => format!("Failed with {status}"),
+            stderr => stderr.lines().next().unwrap_or(stderr).to_string(),
+        }),
+        Err(err) => Err(format!("Cannot wait for the analysis, err: {err}")),
+    };
+    MeasuredRun { output, seconds, peak_memory_kb }
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>)
+-> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut content = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut content);
+        }
+        content
+    })
+}
+
+/// The peak resident memory of a running process in kB, the line `VmHWM`
+/// of `/proc/{pid}/status`. `None` on other systems than Linux.
+pub fn peak_memory(pid: u32) -> Option<u64> {
+    fs::read_to_string(format!("/proc/{pid}/status")).ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn wildcard_match_literal() {
+        assert!(wildcard_match("model.aeon", "model.aeon"));
+        assert!(!wildcard_match("model.aeon", "model.aeonx"));
+        assert!(!wildcard_match("model.aeon", "model.aeo"));
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "a"));
+    }
+
+    #[test]
+    fn wildcard_match_star() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("*_fpared.aeon", "LAC-OPERON_fpared.aeon"));
+        assert!(!wildcard_match("*_fpared.aeon", "LAC-OPERON.aeon"));
+        assert!(wildcard_match("a*b*c", "abc"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
+        assert!(wildcard_match("**a", "a"));
+    }
+
+    #[test]
+    fn wildcard_match_question_mark() {
+        assert!(wildcard_match("?", "é"));
+        assert!(!wildcard_match("?", ""));
+        assert!(!wildcard_match("?", "ab"));
+        assert!(wildcard_match("model_?.aeon", "model_1.aeon"));
+        assert!(!wildcard_match("model_?.aeon", "model_12.aeon"));
+        assert!(wildcard_match("?*?", "ab"));
+        assert!(!wildcard_match("?*?", "a"));
+    }
+}
@@ -1,18 +1,28 @@
 extern crate websocket;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
 
-use pbn_ibmfa::utils::{add_self_regulations};
+use pbn_ibmfa::utils::{add_self_regulations, vertices_to_bitstrings};
+use pbn_ibmfa::parameter_names::parameter_name;
+use pbn_ibmfa::model_format::read_model;
 use pbn_ibmfa::symbolic_sync_graph::SymbSyncGraph;
 use pbn_ibmfa::driver_set::{colors_partition, find_driver_set,
-    fixes::DriverSet};
-use pbn_ibmfa::decision_tree::{DecisionTree, decision_tree_from_partition};
+    driver_set_to_json, driver_set_from_json, fixes::DriverSet};
+use pbn_ibmfa::driver_set::phenotype::Phenotype;
+use pbn_ibmfa::decision_tree::{DecisionTree, SplitPredicate, TreeLimits,
+    Behaviour, BehaviourKind, LeafLabel, decision_tree_from_partition,
+    behaviour_partition, prune,
+    serialization::{tree_to_json, tree_from_json}};
+use pbn_ibmfa::experiment_spec::read_spec;
 
 use biodivine_lib_param_bn::{BooleanNetwork,
     symbolic_async_graph::{GraphColoredVertices, GraphColors, SymbolicContext}};
+use biodivine_lib_bdd::Bdd;
 
 use websocket::{sync::{Server, Client, Stream}, OwnedMessage};
 use clap::Parser;
+use json::{JsonValue, object};
+use sha2::{Digest, Sha256};
 
 
 /// Run the server.
@@ -24,34 +34,183 @@ struct Cli {
     /// Address the server listens to.
     #[arg(short, long, default_value_t = String::from("127.0.0.1"))]
     addr: String,
+    /// Keep the decision trees in the directory across sessions.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Length of the simulations of the driver-set search.
+    #[arg(short, long, default_value_t = 10)]
+    time_steps: usize,
+    /// Take the settings of the driver-set search and of the decision trees
+    /// from an `analysis` spec of `pbn_ibmfa` (TOML if .toml, JSON
+    /// otherwise), e.g. the resolved `spec` of its output. Used are
+    /// `time_steps`, `not_reduced`, `max_conjunction`, `max_depth`,
+    /// `min_leaf_size`, `min_gain` and `prune`, the model and the other
+    /// arguments are ignored.
+    #[arg(long, value_name = "FILE", conflicts_with = "time_steps")]
+    spec: Option<PathBuf>,
 }
 
-const ITERATIONS: usize = 10;
+/// The settings of the driver-set search and of the decision trees,
+/// the arguments of `pbn_ibmfa analysis` of the same names.
+#[derive(Clone, Copy, Debug)]
+struct Settings {
+    time_steps: usize,
+    reduced: bool,
+    max_conjunction: usize,
+    limits: TreeLimits,
+    prune: Option<f64>,
+}
+
+impl Settings {
+    fn new(time_steps: usize) -> Self {
+        Settings {
+            time_steps,
+            reduced: true,
+            max_conjunction: 2,
+            limits: TreeLimits::default(),
+            prune: None,
+        }
+    }
+
+    /// Overrides the settings by the arguments of `spec`, grouped in tables
+    /// freely as in `pbn_ibmfa --spec`.
+    fn apply_spec(&mut self, spec: &JsonValue) -> Result<(), String> {
+        match spec["command"].as_str() {
+            None | Some("analysis") => {},
+            Some(command) => return Err(format!(
+                "Expected an 'analysis' spec, not '{command}'.")),
+        }
+        match &spec["semantics"] {
+            JsonValue::Null => {},
+            semantics if semantics == "synchronous" => {},
+            semantics => return Err(format!("Unsupported semantics \
+                '{semantics}', only 'synchronous'.")),
+        }
+
+        let arguments = spec.entries()
+            .flat_map(|(key, value)| match value.is_object() {
+                true => value.entries().collect::<Vec<_>>(),
+                false => vec![(key, value)],
+            });
+        for (key, value) in arguments {
+            let invalid = || format!("Invalid '{key}' in the spec.");
+            match key {
+                "time_steps" => self.time_steps =
+                    value.as_usize().ok_or_else(invalid)?,
+                "not_reduced" => self.reduced =
+                    !value.as_bool().ok_or_else(invalid)?,
+                "max_conjunction" => self.max_conjunction =
+                    value.as_usize().ok_or_else(invalid)?,
+                "max_depth" => self.limits.max_depth =
+                    Some(value.as_usize().ok_or_else(invalid)?),
+                "min_leaf_size" => self.limits.min_leaf_size =
+                    value.as_f64().ok_or_else(invalid)?,
+                "min_gain" => self.limits.min_gain =
+                    value.as_f64().ok_or_else(invalid)?,
+                "prune" => self.prune =
+                    Some(value.as_f64().ok_or_else(invalid)?),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// A decision tree of `partition` of `colors` by the settings.
+    fn decision_tree<L: LeafLabel>(
+        &self,
+        sync_graph: &SymbSyncGraph,
+        colors: &Bdd,
+        partition: &[(Bdd, L)],
+    ) -> DecisionTree<L> {
+        let tree = decision_tree_from_partition(sync_graph, colors,
+            partition, self.max_conjunction, &self.limits);
+        match self.prune {
+            Some(alpha) => prune(&tree, colors, alpha),
+            None => tree,
+        }
+    }
+}
 
 struct SessionData {
     sync_graph: Option<SymbSyncGraph>,
     attrs: Option<Vec<GraphColoredVertices>>,
     cache: HashMap<usize, (DecisionTree, DriverSet)>,
+    /// Directory of the cached trees of the current model.
+    cache_dir: Option<PathBuf>,
+    settings: Settings,
 }
 
 impl SessionData {
-    fn new() -> Self {
+    fn new(settings: Settings) -> Self {
         SessionData {
             sync_graph: None,
             attrs: None,
             cache: HashMap::new(),
+            cache_dir: None,
+            settings,
         }
     }
 }
 
 
+// The format (.aeon or SBML-qual) is detected by the content
 fn open_model(data: &[u8]) -> Result<BooleanNetwork, String> {
     match std::str::from_utf8(data) {
-        Ok(model_str) => BooleanNetwork::try_from(model_str),
+        Ok(model_str) => read_model(model_str, None),
         Err(_) => Err("Cannot read the file".into()),
     }
 }
 
+// The trees of a model are cached in a subdirectory named by the SHA-256
+// hash of the model file and the settings, stable across builds
+fn model_cache_dir(cache_dir: &Path, data: &[u8], settings: &Settings)
+-> PathBuf {
+    let hash = Sha256::new()
+        .chain_update(data)
+        .chain_update((settings.time_steps as u64).to_le_bytes())
+        .chain_update([settings.reduced as u8])
+        .chain_update((settings.max_conjunction as u64).to_le_bytes())
+        .chain_update(settings.limits.max_depth
+            .map_or(u64::MAX, |depth| depth as u64).to_le_bytes())
+        .chain_update(settings.limits.min_leaf_size.to_le_bytes())
+        .chain_update(settings.limits.min_gain.to_le_bytes())
+        .chain_update(settings.prune.unwrap_or(-1.0).to_le_bytes())
+        .finalize();
+    cache_dir.join(hash.iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>())
+}
+
+fn load_cached_tree(path: &Path, sync_graph: &SymbSyncGraph)
+-> Result<(DecisionTree, DriverSet), String> {
+    let data = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|data_str| json::parse(&data_str)
+            .map_err(|err| err.to_string()))?;
+    let (tree, _) = tree_from_json(&data["tree"], sync_graph,
+        &|label| driver_set_from_json(label, sync_graph))?;
+    let driver_set = driver_set_from_json(&data["driver_set"], sync_graph)?;
+    Ok((tree, driver_set))
+}
+
+fn save_cached_tree(
+    path: &Path,
+    tree: &DecisionTree,
+    driver_set: &DriverSet,
+    colors: &GraphColors,
+    sync_graph: &SymbSyncGraph,
+) -> Result<(), String> {
+    let context = sync_graph.symbolic_context();
+    let data = object!{
+        driver_set: driver_set_to_json(driver_set, context),
+        tree: tree_to_json(tree, colors, sync_graph,
+            &|driver_set| driver_set_to_json(driver_set, context)),
+    };
+    fs::create_dir_all(path.parent().unwrap())
+        .and_then(|_| fs::write(path, json::stringify(data)))
+        .map_err(|err| err.to_string())
+}
+
 fn attrs_to_msg(attrs: &[GraphColoredVertices], context: &SymbolicContext)
 -> OwnedMessage {
     let mut msg_str = attrs.iter()
@@ -78,10 +237,26 @@ fn tree_and_dset_to_msg(
     colors: &GraphColors,
     sync_graph: &SymbSyncGraph
 ) -> OwnedMessage {
+    let context = sync_graph.symbolic_context();
     let mut buffer = String::new();
-    driver_set_to_str(dset, sync_graph.symbolic_context(), &mut buffer);
+    driver_set_to_str(dset, context, &mut buffer);
     buffer.push(' ');
-    tree_to_str_rec(tree, colors, sync_graph, &mut buffer);
+    tree_to_str_rec(tree, colors, sync_graph,
+        &|driver_set, out| driver_set_to_str(driver_set, context, out),
+        &mut buffer);
+    OwnedMessage::Text(buffer)
+}
+
+fn behaviour_tree_to_msg(
+    tree: &DecisionTree<Behaviour>,
+    colors: &GraphColors,
+    sync_graph: &SymbSyncGraph
+) -> OwnedMessage {
+    let context = sync_graph.symbolic_context();
+    let mut buffer = String::new();
+    tree_to_str_rec(tree, colors, sync_graph,
+        &|behaviour, out| behaviour_to_str(behaviour, context, out),
+        &mut buffer);
     OwnedMessage::Text(buffer)
 }
 
@@ -102,32 +277,74 @@ fn driver_set_to_str(
     out.push_str(" ]");
 }
 
-fn tree_to_str_rec(
-    tree: &DecisionTree,
+// Attractor states as bitstrings, the number of fixed points,
+// or 0/1 for the phenotype reachability
+fn behaviour_to_str(
+    behaviour: &Behaviour,
+    context: &SymbolicContext,
+    out: &mut String,
+) {
+    out.push('[');
+    match behaviour {
+        Behaviour::Attractors(vertices) => {
+            for bitstring in vertices_to_bitstrings(vertices, context) {
+                out.push(' ');
+                out.push_str(&bitstring);
+            }
+        },
+        Behaviour::FixedPoints(num) => out.push_str(&format!(" {num}")),
+        Behaviour::PhenotypeReachable(reachable) =>
+            out.push_str(if *reachable { " 1" } else { " 0" }),
+    }
+    out.push_str(" ]");
+}
+
+fn tree_to_str_rec<L>(
+    tree: &DecisionTree<L>,
     colors: &GraphColors,
     sync_graph: &SymbSyncGraph,
+    leaf_to_str: &dyn Fn(&L, &mut String),
     out: &mut String
 ) {
-    let context = sync_graph.symbolic_context();
-    let bdd_var_set = context.bdd_variable_set();
     match tree {
-        DecisionTree::Leaf(driver_set) => {
-            driver_set_to_str(driver_set, context, out);
+        DecisionTree::Leaf(label) => {
+            leaf_to_str(label, out);
+        },
+        // Not built by the server, shown by the largest class
+        DecisionTree::ImpureLeaf(labels) => {
+            leaf_to_str(&labels[0].1, out);
         },
         DecisionTree::Node(node) => {
             let fix_bdd = node.get_fix();
             let fix_false = colors.copy(colors.as_bdd().and_not(fix_bdd));
             let fix_true = colors.copy(colors.as_bdd().and(fix_bdd));
 
-            // Add context for the fixing parameter variables
-            for (bdd_var, val) in fix_bdd.first_clause().unwrap().to_values() {
-                let fix_name = bdd_var_set.name_of(bdd_var);
-                let name = contextify_fix_name(&fix_name, sync_graph);
-                if !val {
-                    out.push('!');
+            for (predicate, value) in node.get_predicates() {
+                match predicate {
+                    // Add context for the fixing parameter variables
+                    SplitPredicate::Parameters(valuation)
+                            if *value || valuation.to_values().len() == 1 => {
+                        for (bdd_var, val) in valuation.to_values() {
+                            let name = parameter_name(bdd_var, sync_graph);
+                            if val != *value {
+                                out.push('!');
+                            }
+                            out.push_str(&name);
+                            out.push(';');
+                        }
+                    },
+                    // Spaces separate the message items
+                    _ => {
+                        let name =
+                            predicate.to_str(sync_graph).replace(' ', "");
+                        if *value {
+                            out.push_str(&name);
+                        } else {
+                            out.push_str(&format!("!({name})"));
+                        }
+                        out.push(';');
+                    },
                 }
-                out.push_str(&name);
-                out.push(';');
             }
             out.pop();
 
@@ -136,49 +353,45 @@ fn tree_to_str_rec(
             out.push(' ');
             out.push_str(&fix_true.exact_cardinality().to_str_radix(10));
             out.push(' ');
-            tree_to_str_rec(&node.get_childs()[0], &fix_false, sync_graph, out);
+            tree_to_str_rec(&node.get_childs()[0], &fix_false, sync_graph,
+                leaf_to_str, out);
             out.push(' ');
-            tree_to_str_rec(&node.get_childs()[1], &fix_true, sync_graph, out);
+            tree_to_str_rec(&node.get_childs()[1], &fix_true, sync_graph,
+                leaf_to_str, out);
         }
     }
 }
 
-// f_{target name}[{regulators}]
-// => {target name}({0/1{name} for each regulator})
-// else return the same as input
-fn contextify_fix_name(fix_name: &str, sync_graph: &SymbSyncGraph) -> String {
-    let mut fix_opt: Option<String> = None;
-
-    if fix_name.starts_with("f_") {
-        if let Some(index) = fix_name.find('[') {
-            let name = &fix_name[2..index];
-            let args = &fix_name[index + 1..fix_name.len() - 1];
-            let reg_graph = sync_graph.as_network().as_graph();
-            if let Some(var_id) = reg_graph.find_variable(name) {
-                let mut result = sync_graph.as_network()
-                    .regulators(var_id)
-                    .iter()
-                    .zip(args.split(','))
-                    .fold(format!("{name}("), |mut acc, (reg_id, val)| {
-                        acc.push_str(val);
-                        acc.push_str(sync_graph.as_network()
-                            .get_variable_name(*reg_id));
-                        acc.push(',');
-                        acc
-                    });
-                result.pop();
-                result.push(')');
-                fix_opt = Some(result);
-            }
-        }
+// ATTRACTORS | FIXED-POINTS | PHENOTYPE {name}={0/1} ...
+fn parse_behaviour_kind(kind: &str, sync_graph: &SymbSyncGraph)
+-> Result<BehaviourKind, String> {
+    let mut words = kind.split(' ');
+    match words.next() {
+        Some("ATTRACTORS") => Ok(BehaviourKind::Attractors),
+        Some("FIXED-POINTS") => Ok(BehaviourKind::FixedPoints),
+        Some("PHENOTYPE") => words
+            .map(|value_str| {
+                let (name, value) = value_str.split_once('=')
+                    .ok_or(format!("Error: invalid phenotype '{value_str}'"))?;
+                let var_id = sync_graph.as_network().as_graph()
+                    .find_variable(name)
+                    .ok_or(format!("Error: unknown variable '{name}'"))?;
+                match value {
+                    "0" => Ok((var_id, false)),
+                    "1" => Ok((var_id, true)),
+                    _ => Err(format!("Error: invalid value '{value}'")),
+                }
+            })
+            .collect::<Result<Phenotype, String>>()
+            .map(BehaviourKind::Phenotype),
+        _ => Err(format!("Error: unexpected behaviour '{kind}'")),
     }
-
-    if let Some(fix) = fix_opt { fix } else { fix_name.into() }
 }
 
 fn get_response(msg: OwnedMessage, session_data: &mut SessionData)
 -> Result<OwnedMessage, String> {
     let sync_graph = session_data.sync_graph.as_ref().unwrap();
+    let settings = session_data.settings;
     let context = sync_graph.symbolic_context();
     match msg {
         OwnedMessage::Text(msg) => {
@@ -196,28 +409,55 @@ fn get_response(msg: OwnedMessage, session_data: &mut SessionData)
                     Some(attrs) => {
                         let id = msg.rsplit(' ').next().unwrap()
                             .parse::<usize>().unwrap();
+                        let cache_path = session_data.cache_dir.as_ref()
+                            .map(|dir| dir.join(format!("attractor_{id}.json")));
                         let (dtree, dset) = session_data.cache
                             .entry(id)
                             .or_insert_with(|| {
+                                if let Some(path) = cache_path.as_ref()
+                                        .filter(|path| path.exists()) {
+                                    match load_cached_tree(path, sync_graph) {
+                                        Ok(cached) => return cached,
+                                        Err(err) => println!(
+                                            "Error: cached tree {path:?}: {err}"),
+                                    }
+                                }
                                 let attr = &attrs[id];
                                 let attr = (&attr.vertices(), &attr.colors());
                                 let driver_sets = colors_partition(
-                                    sync_graph, ITERATIONS, true, attr, false);
-                                let dtree = decision_tree_from_partition(
+                                    sync_graph, settings.time_steps,
+                                    settings.reduced, attr, false);
+                                let dtree = settings.decision_tree(
+                                    sync_graph,
                                     attr.1.as_bdd(),
                                     driver_sets.as_slice(),
-                                    context.bdd_variable_set()
                                 );
                                 let (pbn_fix, _) = find_driver_set(
-                                    sync_graph, ITERATIONS,
-                                    true, Some(attr), true, false
+                                    sync_graph, settings.time_steps,
+                                    settings.reduced, Some(attr), true, false
                                 );
-                                (dtree, pbn_fix.get_driver_set().clone())
+                                let dset = pbn_fix.get_driver_set().clone();
+                                if let Some(path) = &cache_path {
+                                    if let Err(err) = save_cached_tree(
+                                            path, &dtree, &dset, attr.1,
+                                            sync_graph) {
+                                        println!("Error: cannot cache \
+                                            {path:?}: {err}");
+                                    }
+                                }
+                                (dtree, dset)
                             });
                         Ok(tree_and_dset_to_msg(
-                            &dtree, &dset, &attrs[id].colors(), sync_graph))
+                            dtree, dset, &attrs[id].colors(), sync_graph))
                     }
                 }
+            } else if let Some(kind) = msg.strip_prefix("BEHAVIOUR ") {
+                let kind = parse_behaviour_kind(kind, sync_graph)?;
+                let colors = sync_graph.unit_colors();
+                let partition = behaviour_partition(sync_graph, &kind, &colors);
+                let tree = settings.decision_tree(
+                    sync_graph, colors.as_bdd(), &partition);
+                Ok(behaviour_tree_to_msg(&tree, &colors, sync_graph))
             } else {
                 Err(format!("Error: unexpected command '{msg:?}'"))
             }
@@ -228,7 +468,8 @@ fn get_response(msg: OwnedMessage, session_data: &mut SessionData)
 
 fn session_loop<S: Stream>(
     client: &mut Client<S>,
-    session_data: &mut SessionData
+    session_data: &mut SessionData,
+    cache_dir: Option<&PathBuf>,
 ) -> bool {
     let msg = client.recv_message().unwrap();
     match msg {
@@ -252,6 +493,9 @@ fn session_loop<S: Stream>(
                     &OwnedMessage::Text(msg_str)).unwrap();
 
                 session_data.sync_graph = Some(sync_graph);
+                session_data.cache_dir =
+                    cache_dir.map(|dir| model_cache_dir(
+                        dir, &vec, &session_data.settings));
             },
             Err(err) => client.send_message(
                 &OwnedMessage::Text(format!("ERR {}", &err))).unwrap(),
@@ -283,6 +527,14 @@ fn session_loop<S: Stream>(
 
 fn main() {
     let args = Cli::parse();
+    let mut settings = Settings::new(args.time_steps);
+    if let Some(path) = &args.spec {
+        if let Err(err) = read_spec(path)
+                .and_then(|spec| settings.apply_spec(&spec)) {
+            eprintln!("Err: {err}");
+            std::process::exit(1);
+        }
+    }
     let addr_port = format!("{}:{}", args.addr, args.port);
     let mut server = Server::bind(&addr_port).unwrap();
 
@@ -295,10 +547,11 @@ fn main() {
         let ip = client.peer_addr().unwrap();
         println!("Connection from {}", ip);
 
-        let mut session_data = SessionData::new();
+        let mut session_data = SessionData::new(settings);
 
         loop {
-            if !session_loop(&mut client, &mut session_data) {
+            if !session_loop(
+                    &mut client, &mut session_data, args.cache_dir.as_ref()) {
                 break;
             }
         }
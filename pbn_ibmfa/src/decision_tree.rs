@@ -1,79 +1,457 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
-use biodivine_lib_bdd::{Bdd, BddVariableSet};
+use biodivine_lib_bdd::{Bdd, BddPartialValuation};
+use biodivine_lib_param_bn::VariableId;
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
 use biodivine_lib_param_bn::symbolic_async_graph::
-    {GraphVertices, GraphColors, SymbolicContext};
+    {GraphColoredVertices, GraphVertices, GraphColors, SymbolicContext};
 
-use crate::ibmfa_computations::minimize_entropy;
-use crate::driver_set::{find_driver_set, driver_set_to_str, PBNFix, UnitFix,
-    fixes::{DriverSet, UnitParameterFix}};
+use crate::ibmfa_computations::ibmfa_entropy;
+use crate::driver_set::{colors_partition_counted, controlled_colors,
+    find_parameter_driver_set_counted, reduce_driver_set_counted,
+    driver_set_to_str, PBNFix, UnitFix, fixes::{DriverSet, UnitVertexFix}};
 use crate::symbolic_sync_graph::SymbSyncGraph;
-use crate::utils::bdd_to_str;
+use crate::driver_set::phenotype::{Phenotype, phenotype_attractor_colors};
+use crate::experiment_design::fixed_point_classes;
+use crate::parameter_names::parameter_name;
+use crate::utils::{combinations, function_to_str,
+    variations_with_replacement, vertices_to_bitstrings, vertices_to_str};
+
+
+pub mod serialization;
+
+
+/// Maximal number of distinct update functions of one variable used
+/// as split candidates.
+const MAX_FUNCTIONS: usize = 256;
+
+/// A condition on colors in a decision node.
+#[derive(Clone, Debug)]
+pub enum SplitPredicate {
+    /// Conjunction of parameter literals, i.e. rows of function tables.
+    Parameters(BddPartialValuation),
+    /// The update function of the variable is exactly the given function
+    /// of state variables.
+    Function(VariableId, Bdd),
+    /// The regulator (first) is essential in the update function
+    /// of the target (second).
+    Essential(VariableId, VariableId),
+}
+
+impl SplitPredicate {
+    /// Parameters are named by `parameter_name`.
+    pub fn to_str(&self, sync_graph: &SymbSyncGraph) -> String {
+        let context = sync_graph.symbolic_context();
+        let var_name = |var_id: &VariableId| context.bdd_variable_set()
+            .name_of(context.get_state_variable(*var_id));
+        match self {
+            SplitPredicate::Parameters(valuation) => valuation.to_values()
+                .iter()
+                .map(|&(bdd_var, value)| format!("{}{}",
+                    if value { "" } else { "!" },
+                    parameter_name(bdd_var, sync_graph)))
+                .collect::<Vec<_>>()
+                .join(" & "),
+            SplitPredicate::Function(var_id, function) => format!("{} = {}",
+                var_name(var_id), function_to_str(function, context)),
+            SplitPredicate::Essential(regulator, target) => format!(
+                "{} essential in {}", var_name(regulator), var_name(target)),
+        }
+    }
+
+    /// Colors of `sync_graph` satisfying the predicate.
+    pub fn colors(&self, sync_graph: &SymbSyncGraph) -> Bdd {
+        match self {
+            SplitPredicate::Parameters(valuation) => sync_graph
+                .symbolic_context().bdd_variable_set()
+                .mk_conjunctive_clause(valuation),
+            SplitPredicate::Function(var_id, function) =>
+                sync_graph.function_colors(*var_id, function),
+            SplitPredicate::Essential(regulator, target) =>
+                sync_graph.essential_colors(*regulator, *target),
+        }
+    }
+}
+
+/// A label of leaves of a decision tree, e.g. a driver-set.
+pub trait LeafLabel: Clone + PartialEq {
+    fn to_str(&self, context: &SymbolicContext) -> String;
+
+    /// Label in DOT (Graphviz) format, see `DecisionTree::to_dot`.
+    fn to_dot_str(&self, context: &SymbolicContext) -> String {
+        self.to_str(context)
+    }
+}
+
+impl LeafLabel for DriverSet {
+    fn to_str(&self, context: &SymbolicContext) -> String {
+        driver_set_to_str(self, context)
+    }
+
+    fn to_dot_str(&self, context: &SymbolicContext) -> String {
+        let driver_set_str = self.iter()
+            .map(|(var_id, value)| format!("{}={}",
+                context.bdd_variable_set()
+                    .name_of(context.get_state_variable(*var_id)),
+                *value as u8))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{{{driver_set_str}}}")
+    }
+}
 
 #[derive(Clone, Debug)]
-pub struct DecisionNode {
-    childs: [Box<DecisionTree>; 2],
+pub struct DecisionNode<L = DriverSet> {
+    childs: [Box<DecisionTree<L>>; 2],
     color_fix: Bdd,
+    predicates: Vec<(SplitPredicate, bool)>,
 }
 
 #[derive(Clone, Debug)]
-pub enum DecisionTree {
-    Node(DecisionNode),
-    Leaf(DriverSet),
+pub enum DecisionTree<L = DriverSet> {
+    Node(DecisionNode<L>),
+    Leaf(L),
+    /// Leaf of a limited or pruned tree with several labels (driver-sets),
+    /// each with its colors. Sorted by the number of colors, descending.
+    ImpureLeaf(Vec<(Bdd, L)>),
+}
+
+/// Limits of a decision tree. A node violating them becomes an impure leaf.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TreeLimits {
+    /// Maximal number of decision nodes on a path from the root.
+    pub max_depth: Option<usize>,
+    /// Minimal number of colors in both children of a decision node.
+    pub min_leaf_size: f64,
+    /// Minimal information gain of a decision node.
+    pub min_gain: f64,
 }
 
-impl DecisionNode {
-    pub fn get_childs(&self) -> &[Box<DecisionTree>; 2] {
+impl<L> DecisionNode<L> {
+    pub fn get_childs(&self) -> &[Box<DecisionTree<L>>; 2] {
         &self.childs
     }
 
     pub fn get_fix(&self) -> &Bdd {
         &self.color_fix
     }
+
+    /// Predicates with their values whose conjunction gives `get_fix()`.
+    pub fn get_predicates(&self) -> &[(SplitPredicate, bool)] {
+        &self.predicates
+    }
+
+    pub fn predicates_to_str(&self, sync_graph: &SymbSyncGraph) -> String {
+        self.predicates.iter()
+            .map(|(predicate, value)| (predicate.to_str(sync_graph), *value))
+            .map(|(predicate_str, value)| if value { predicate_str } else {
+                format!("!({predicate_str})")
+            })
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
 }
 
-impl DecisionTree {
-    pub fn to_str(&self, context: &SymbolicContext) -> String {
-        self.to_str_rec(0, context)
+impl<L: LeafLabel> DecisionTree<L> {
+    /// Label of the leaf deciding `colors`, e.g. a single color.
+    ///
+    /// Returns `None` if the colors are split by a decision node
+    /// or by the classes of an impure leaf.
+    pub fn evaluate(&self, colors: &Bdd) -> Option<&L> {
+        match self {
+            DecisionTree::Node(node) => {
+                let fix = node.get_fix();
+                if colors.and_not(fix).is_false() {
+                    node.childs[1].evaluate(colors)
+                } else if colors.and(fix).is_false() {
+                    node.childs[0].evaluate(colors)
+                } else {
+                    None
+                }
+            },
+            DecisionTree::Leaf(label) => Some(label),
+            DecisionTree::ImpureLeaf(labels) => labels.iter()
+                .find(|(class, _)| colors.and_not(class).is_false())
+                .map(|(_, label)| label),
+        }
+    }
+
+    /// Number of decision nodes.
+    pub fn num_nodes(&self) -> usize {
+        match self {
+            DecisionTree::Node(node) =>
+                1 + node.childs.iter()
+                    .map(|child| child.num_nodes())
+                    .sum::<usize>(),
+            _ => 0,
+        }
     }
 
-    fn to_str_rec(&self, level: usize, context: &SymbolicContext) -> String {
+    pub fn num_leaves(&self) -> usize {
+        match self {
+            DecisionTree::Node(node) =>
+                node.childs.iter().map(|child| child.num_leaves()).sum(),
+            _ => 1,
+        }
+    }
+
+    /// Number of decision nodes on the longest path from the root.
+    pub fn depth(&self) -> usize {
+        match self {
+            DecisionTree::Node(node) => 1 + node.childs.iter()
+                .map(|child| child.depth())
+                .max()
+                .unwrap(),
+            _ => 0,
+        }
+    }
+
+    /// Classes of `colors` decided by the leaves with their labels.
+    pub fn leaf_classes(&self, colors: &Bdd) -> Vec<(Bdd, L)> {
+        match self {
+            DecisionTree::Node(node) => {
+                let fix = node.get_fix();
+                let mut classes =
+                    node.childs[0].leaf_classes(&colors.and_not(fix));
+                classes.extend(
+                    node.childs[1].leaf_classes(&colors.and(fix)));
+                classes
+            },
+            DecisionTree::Leaf(label) => vec![(colors.clone(), label.clone())],
+            DecisionTree::ImpureLeaf(labels) => labels.iter()
+                .map(|(class, label)| (class.and(colors), label.clone()))
+                .filter(|(class, _)| !class.is_false())
+                .collect(),
+        }
+    }
+
+    pub fn to_str(&self, sync_graph: &SymbSyncGraph) -> String {
+        self.to_str_rec(0, sync_graph)
+    }
+
+    fn to_str_rec(&self, level: usize, sync_graph: &SymbSyncGraph) -> String {
+        let context = sync_graph.symbolic_context();
         match self {
             DecisionTree::Node(node) => {
                 let indent = " ".repeat(level);
                 format!("{}\n{indent}-0- {}\n{indent}-1- {}",
-                    bdd_to_str(&node.color_fix, context),
-                    node.childs[0].to_str_rec(level + 4, context),
-                    node.childs[1].to_str_rec(level + 4, context))
+                    node.predicates_to_str(sync_graph),
+                    node.childs[0].to_str_rec(level + 4, sync_graph),
+                    node.childs[1].to_str_rec(level + 4, sync_graph))
                 },
-            DecisionTree::Leaf(driver_set) =>
-                driver_set_to_str(driver_set, context),
+            DecisionTree::Leaf(label) => label.to_str(context),
+            DecisionTree::ImpureLeaf(labels) => {
+                let total = labels.iter()
+                    .map(|(colors, _)| colors.cardinality())
+                    .sum::<f64>();
+                labels.iter()
+                    .map(|(colors, label)| format!("{} ({:.1}%)",
+                        label.to_str(context),
+                        100.0 * colors.cardinality() / total))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            },
+        }
+    }
+
+    /// Graphviz (DOT) representation of the tree.
+    ///
+    /// Nodes show the split predicates with readable parameter names,
+    /// leaves their labels. Both show the number of their colors,
+    /// the dashed edges lead to the colors not satisfying the predicates.
+    ///
+    /// * `colors` - The colors the tree decides.
+    pub fn to_dot(&self, colors: &GraphColors, sync_graph: &SymbSyncGraph)
+    -> String {
+        let mut out = String::from("digraph decision_tree {\n");
+        self.to_dot_rec(colors, sync_graph, &mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_dot_rec(
+        &self,
+        colors: &GraphColors,
+        sync_graph: &SymbSyncGraph,
+        next_id: &mut usize,
+        out: &mut String,
+    ) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let colors_num = colors.approx_cardinality();
+        match self {
+            DecisionTree::Node(node) => {
+                let label = node.predicates_to_str(sync_graph)
+                    .replace('"', "\\\"");
+                out.push_str(&format!(
+                    "    n{id} [shape=box, label=\"{label}\\n\
+                    {colors_num} colors\"];\n"));
+                let fix = node.get_fix();
+                let split = [
+                    colors.copy(colors.as_bdd().and_not(fix)),
+                    colors.copy(colors.as_bdd().and(fix)),
+                ];
+                for (child, (child_colors, style)) in node.get_childs().iter()
+                        .zip(split.iter().zip(["dashed", "solid"])) {
+                    let child_id =
+                        child.to_dot_rec(child_colors, sync_graph, next_id, out);
+                    out.push_str(&format!(
+                        "    n{id} -> n{child_id} [style={style}];\n"));
+                }
+            },
+            DecisionTree::Leaf(label) => {
+                let label_str = label.to_dot_str(sync_graph.symbolic_context());
+                out.push_str(&format!(
+                    "    n{id} [shape=ellipse, label=\"{label_str}\\n\
+                    {colors_num} colors\"];\n"));
+            },
+            DecisionTree::ImpureLeaf(labels) => {
+                let total = labels.iter()
+                    .map(|(colors, _)| colors.cardinality())
+                    .sum::<f64>();
+                let labels_str = labels.iter()
+                    .map(|(colors, label)| format!("{} {:.1}%",
+                        label.to_dot_str(sync_graph.symbolic_context()),
+                        100.0 * colors.cardinality() / total))
+                    .collect::<Vec<_>>()
+                    .join("\\n");
+                out.push_str(&format!(
+                    "    n{id} [shape=ellipse, style=dashed, \
+                    label=\"{labels_str}\\n{colors_num} colors\"];\n"));
+            },
         }
+        id
     }
 }
 
 
+/*******************************************************************************
+ * Split candidates
+ * ================
+ * Besides single parameters (rows of function tables), colors may be split
+ * by conjunctions of a few parameters, by an exact update function of
+ * a variable, or by essentiality of a regulation. The latter two are
+ * listed first, so they are preferred when equally informative.
+ ******************************************************************************/
+
+/// Lists the predicates splitting `colors` into two non-empty parts,
+/// each with the colors where it holds.
+///
+/// * `max_conjunction` - The maximal number of parameter literals
+///     in a conjunction.
+pub fn split_candidates(
+    sync_graph: &SymbSyncGraph,
+    colors: &Bdd,
+    max_conjunction: usize,
+) -> Vec<(SplitPredicate, Bdd)> {
+    let context = sync_graph.symbolic_context();
+    let bdd_var_set = context.bdd_variable_set();
+    let network = sync_graph.as_network();
+    let mut candidates = Vec::new();
+
+    // Exact update functions
+    for (var_id, pupdate_function) in
+            network.variables().zip(sync_graph.get_pupdate_functions()) {
+        let pars = pupdate_function.get_parameters();
+        if pars.is_empty() {
+            continue;
+        }
+        let parametrizations = pupdate_function
+            .restricted_parametrizations(colors.clone())
+            .and(&sync_graph.get_all_false().project(pars))
+            .sat_valuations()
+            .take(MAX_FUNCTIONS + 1)
+            .collect::<Vec<_>>();
+        if parametrizations.len() > MAX_FUNCTIONS {
+            continue;
+        }
+
+        let mut functions: Vec<(Bdd, Bdd)> = Vec::new();
+        let mut function_index = HashMap::new();
+        for parametrization in parametrizations {
+            let function = pupdate_function.restricted(&parametrization);
+            let values = pars.iter()
+                .map(|&bdd_var| (bdd_var, parametrization[bdd_var]))
+                .collect::<Vec<_>>();
+            let clause = bdd_var_set.mk_conjunctive_clause(
+                &BddPartialValuation::from_values(&values));
+            let index = *function_index.entry(function.clone())
+                .or_insert_with(|| {
+                    functions.push((function, bdd_var_set.mk_false()));
+                    functions.len() - 1
+                });
+            functions[index].1 = functions[index].1.or(&clause);
+        }
+        candidates.extend(functions.into_iter()
+            .map(|(function, bdd)|
+                (SplitPredicate::Function(var_id, function), bdd)));
+    }
+
+    // Essential regulations
+    for regulation in network.as_graph().regulations() {
+        let predicate = SplitPredicate::Essential(
+            regulation.get_regulator(), regulation.get_target());
+        let bdd = predicate.colors(sync_graph);
+        candidates.push((predicate, bdd));
+    }
+
+    // Conjunctions of parameters
+    let pars = colors.support_set().into_iter().collect::<Vec<_>>();
+    for size in 1..=max_conjunction {
+        // The negation of a single parameter splits the same way
+        let all_values = if size == 1 {
+            vec![vec![true]]
+        } else {
+            variations_with_replacement(&[false, true], size)
+        };
+        for conj_pars in combinations(&pars, size) {
+            for values in &all_values {
+                let valuation = BddPartialValuation::from_values(
+                    &conj_pars.iter().copied()
+                        .zip(values.iter().copied())
+                        .collect::<Vec<_>>());
+                let bdd = bdd_var_set.mk_conjunctive_clause(&valuation);
+                candidates.push((SplitPredicate::Parameters(valuation), bdd));
+            }
+        }
+    }
+
+    // Only the first of the candidates splitting the same way
+    let mut splits = HashSet::new();
+    candidates.into_iter()
+        .filter(|(_, bdd)| {
+            let split = colors.and(bdd);
+            !split.is_false() && split != *colors
+                && !splits.contains(&colors.and_not(bdd))
+                && splits.insert(split)
+        })
+        .collect()
+}
+
+
 /*******************************************************************************
  * Finding a decision tree for a colors partition
  * ==============================================
  * Having the colors partitioned and assinged a driver set for each
  * partition, find a binary decision tree leading to leaves consisting of
- * the partitions. A decision node contains only a single parameter for now.
+ * the partitions. A decision node is chosen among `split_candidates`
+ * maximizing the information gain.
  ******************************************************************************/
 
 #[derive(Debug, Clone)]
-struct UnresolvedNode {
+struct UnresolvedNode<L> {
     colors: Bdd,
-    driver_sets: Vec<(Bdd, DriverSet)>,
+    classes: Vec<(Bdd, L)>,
 }
 
-impl UnresolvedNode {
+impl<L: LeafLabel> UnresolvedNode<L> {
     fn new(colors: Bdd) -> Self {
-        UnresolvedNode { colors, driver_sets: Vec::new() }
+        UnresolvedNode { colors, classes: Vec::new() }
     }
 
     fn entropy(&self) -> f64 {
-        let e = self.driver_sets.iter()
+        let e = self.classes.iter()
             .map(|(colors, _)| {
                 let c = colors.cardinality();
                 if c == 0.0 { 0.0 } else { c * c.log2() }
@@ -81,43 +459,88 @@ impl UnresolvedNode {
         let total = self.colors.cardinality();
         if total == 0.0 { 0.0 } else { total.log2() - e / total }
     }
+
+    fn weighted_entropy(&self) -> f64 {
+        self.colors.cardinality() * self.entropy()
+    }
+
+    /// The information gain of splitting the node into `split_nodes`,
+    /// i.e. the decrease of entropy weighted by the sizes of the nodes.
+    fn information_gain(&self, split_nodes: &[UnresolvedNode<L>; 2]) -> f64 {
+        (self.weighted_entropy()
+            - split_nodes[0].weighted_entropy()
+            - split_nodes[1].weighted_entropy())
+            / self.colors.cardinality()
+    }
+
+    fn into_leaf(mut self) -> DecisionTree<L> {
+        if self.classes.len() == 1 {
+            DecisionTree::Leaf(self.classes.pop().unwrap().1)
+        } else {
+            self.classes.sort_by(|(c1, _), (c2, _)|
+                c2.cardinality().total_cmp(&c1.cardinality()));
+            DecisionTree::ImpureLeaf(self.classes)
+        }
+    }
 }
 
-pub fn decision_tree_from_partition(
+/// Finds a decision tree for `partition` of `all_colors`.
+///
+/// * `partition` - Classes of colors with their labels, e.g. driver-sets
+///     (see `colors_partition`) or behaviours (see `behaviour_partition`).
+/// * `max_conjunction` - As in `split_candidates`.
+/// * `limits` - Limits of the tree, the default is unlimited.
+pub fn decision_tree_from_partition<L: LeafLabel>(
+    sync_graph: &SymbSyncGraph,
     all_colors: &Bdd,
-    driver_sets: &[(Bdd, DriverSet)],
-    bdd_variable_set: &BddVariableSet,
-) -> DecisionTree {
-    fn split_unresolved(
-        mut node: UnresolvedNode,
-        mut pars: HashSet<Bdd>,
-        bdd_variable_set: &BddVariableSet,
-    ) -> DecisionTree {
-        assert!(!node.driver_sets.is_empty());
-        if node.driver_sets.len() == 1 {
-            return DecisionTree::Leaf(node.driver_sets.pop().unwrap().1);
+    partition: &[(Bdd, L)],
+    max_conjunction: usize,
+    limits: &TreeLimits,
+) -> DecisionTree<L> {
+    fn split_unresolved<L: LeafLabel>(
+        mut node: UnresolvedNode<L>,
+        mut candidates: Vec<(SplitPredicate, Bdd)>,
+        limits: &TreeLimits,
+        depth: usize,
+        sync_graph: &SymbSyncGraph,
+    ) -> DecisionTree<L> {
+        assert!(!node.classes.is_empty());
+        if node.classes.len() == 1
+                || limits.max_depth.is_some_and(|max| depth >= max) {
+            return node.into_leaf();
         }
 
-        let mut bdd = bdd_variable_set.mk_true();
+        let mut bdd = sync_graph.symbolic_context().mk_constant(true);
+        let mut predicates = Vec::new();
         loop {
-            let (par, split_nodes) =
-                best_decision_par(&pars, &node).unwrap();
-            pars.remove(&par);
+            let Some((index, split_nodes, gain)) = best_decision_par(
+                    &candidates, &node, limits.min_leaf_size) else {
+                return node.into_leaf();
+            };
+            if gain < limits.min_gain {
+                return node.into_leaf();
+            }
+            let (predicate, par) = candidates.remove(index);
 
-            if split_nodes[0].driver_sets.is_empty() {
+            if split_nodes[0].classes.is_empty() {
                 bdd = bdd.and(&par);
+                predicates.push((predicate, true));
                 node = split_nodes[1].clone();
-            } else if split_nodes[1].driver_sets.is_empty() {
+            } else if split_nodes[1].classes.is_empty() {
                 bdd = bdd.and_not(&par);
+                predicates.push((predicate, false));
                 node = split_nodes[0].clone();
             } else {
+                predicates.push((predicate, true));
                 let trees = split_nodes.map(|split_node|
                         Box::new(split_unresolved(
-                                split_node, pars.clone(), bdd_variable_set)));
+                                split_node, candidates.clone(), limits,
+                                depth + 1, sync_graph)));
 
                 return DecisionTree::Node(DecisionNode {
                     childs: trees,
-                    color_fix: bdd.and(&par)
+                    color_fix: bdd.and(&par),
+                    predicates,
                 });
             }
         }
@@ -125,142 +548,427 @@ pub fn decision_tree_from_partition(
 
     let node = UnresolvedNode {
         colors: all_colors.clone(),
-        driver_sets: Vec::from(driver_sets)
+        classes: Vec::from(partition)
+    };
+    let candidates = split_candidates(sync_graph, all_colors, max_conjunction);
+
+    // The sizes of color sets are compared as bdds with state variables
+    let context = sync_graph.symbolic_context();
+    let unused_vars = context.bdd_variable_set().num_vars() as usize
+        - context.parameter_variables().len();
+    let limits = TreeLimits {
+        min_leaf_size: limits.min_leaf_size * 2f64.powi(unused_vars as i32),
+        ..*limits
     };
-    let pars = all_colors.support_set().iter()
-        .map(|bdd_var| bdd_variable_set.mk_var(*bdd_var))
-        .collect::<HashSet<_>>();
 
-    split_unresolved(node, pars, bdd_variable_set)
+    split_unresolved(node, candidates, &limits, 0, sync_graph)
 }
 
-fn best_decision_par<'a>(
-    pars: impl IntoIterator<Item = &'a Bdd>,
-    node: &UnresolvedNode,
-) -> Option<(Bdd, [UnresolvedNode; 2])> {
-    // Maximization of information gain = Minimization of information entropy
-    pars.into_iter()
-        .map(|par| {
+/// Returns the index of the candidate with the highest information gain,
+/// the split nodes and the gain. Candidates leaving one side empty are
+/// skipped.
+///
+/// * `min_size` - Candidates splitting off less colors are skipped.
+fn best_decision_par<L: LeafLabel>(
+    candidates: &[(SplitPredicate, Bdd)],
+    node: &UnresolvedNode<L>,
+    min_size: f64,
+) -> Option<(usize, [UnresolvedNode<L>; 2], f64)> {
+    candidates.iter()
+        .enumerate()
+        .filter_map(|(index, (_, par))| {
             let mut split_nodes = [
                 UnresolvedNode::new(node.colors.and_not(par)),
                 UnresolvedNode::new(node.colors.and(par))
             ];
+            if split_nodes.iter()
+                    .any(|split_node| split_node.colors.is_false()) {
+                return None;
+            }
+            if min_size > 0.0 && split_nodes.iter()
+                    .any(|split_node| split_node.colors.cardinality() < min_size) {
+                return None;
+            }
 
-            for (colors, driver_set) in &node.driver_sets {
+            for (colors, label) in &node.classes {
                 for split_node in split_nodes.as_mut_slice() {
                     let subcolors = colors.and(&split_node.colors);
                     if !subcolors.is_false() {
-                        split_node.driver_sets.push(
-                            (subcolors, driver_set.clone()));
+                        split_node.classes.push(
+                            (subcolors, label.clone()));
                     }
                 }
             }
 
-            (par, split_nodes)
-        })
-        .min_by(|(_, sns1), (_, sns2)| {
-            let e1 = sns1[0].entropy() + sns1[1].entropy();
-            let e2 = sns2[0].entropy() + sns2[1].entropy();
-            e1.partial_cmp(&e2).unwrap()
+            let gain = node.information_gain(&split_nodes);
+            Some((index, split_nodes, gain))
         })
-        .map(|(par, sns)| (par.clone(), sns))
+        // The first of the best candidates
+        .min_by(|(_, _, gain1), (_, _, gain2)| gain2.total_cmp(gain1))
+}
+
+
+/*******************************************************************************
+ * Behaviour classes
+ * =================
+ * Colors partitioned by a qualitative behaviour of the network instead of
+ * driver-sets. A tree of such a partition shows which parameters decide
+ * the behaviour.
+ ******************************************************************************/
+
+/// A qualitative behaviour of the network in a class of colors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Behaviour {
+    /// The states of all the attractors.
+    Attractors(GraphVertices),
+    /// The number of fixed points.
+    FixedPoints(usize),
+    /// Whether some attractor satisfies a phenotype.
+    PhenotypeReachable(bool),
+}
+
+/// The kind of behaviour partitioning the colors.
+#[derive(Clone, Debug)]
+pub enum BehaviourKind {
+    Attractors,
+    FixedPoints,
+    Phenotype(Phenotype),
+}
+
+impl LeafLabel for Behaviour {
+    fn to_str(&self, context: &SymbolicContext) -> String {
+        match self {
+            Behaviour::Attractors(vertices) =>
+                vertices_to_str(vertices, context),
+            Behaviour::FixedPoints(1) => String::from("1 fixed point"),
+            Behaviour::FixedPoints(num) => format!("{num} fixed points"),
+            Behaviour::PhenotypeReachable(true) =>
+                String::from("phenotype reachable"),
+            Behaviour::PhenotypeReachable(false) =>
+                String::from("phenotype not reachable"),
+        }
+    }
+
+    fn to_dot_str(&self, context: &SymbolicContext) -> String {
+        match self {
+            // A state per line, the values in order of the state variables
+            Behaviour::Attractors(vertices) =>
+                vertices_to_bitstrings(vertices, context).join("\\n"),
+            _ => self.to_str(context),
+        }
+    }
+}
+
+/// Partitions `colors` by the behaviour of `sync_graph` of the given `kind`.
+///
+/// The phenotype is reachable if some attractor satisfies it,
+/// see `phenotype_attractor_colors`.
+pub fn behaviour_partition(
+    sync_graph: &SymbSyncGraph,
+    kind: &BehaviourKind,
+    colors: &GraphColors,
+) -> Vec<(Bdd, Behaviour)> {
+    match kind {
+        BehaviourKind::Attractors => {
+            let set = sync_graph.unit_colored_vertices().intersect_colors(colors);
+            let attrs = sync_graph.attractors_in(&set).iter()
+                .fold(sync_graph.empty_colored_vertices(),
+                    |acc, attr| acc.union(attr));
+            colors_by_vertices(sync_graph, &attrs, colors).into_iter()
+                .map(|(colors, vertices)|
+                    (colors.into_bdd(), Behaviour::Attractors(vertices)))
+                .collect()
+        },
+        BehaviourKind::FixedPoints => {
+            let mut classes: Vec<(Bdd, Behaviour)> = Vec::new();
+            for (class_colors, vertices) in
+                    fixed_point_classes(sync_graph, colors) {
+                let behaviour = Behaviour::FixedPoints(
+                    vertices.approx_cardinality() as usize);
+                match classes.iter_mut().find(|(_, b)| *b == behaviour) {
+                    Some((colors, _)) =>
+                        *colors = colors.or(class_colors.as_bdd()),
+                    None => classes.push((class_colors.into_bdd(), behaviour)),
+                }
+            }
+            classes
+        },
+        BehaviourKind::Phenotype(phenotype) => {
+            let reachable = phenotype_attractor_colors(
+                sync_graph, &DriverSet::new(), phenotype, colors);
+            [(reachable.clone(), true), (colors.minus(&reachable), false)]
+                .into_iter()
+                .filter(|(colors, _)| !colors.is_empty())
+                .map(|(colors, value)|
+                    (colors.into_bdd(), Behaviour::PhenotypeReachable(value)))
+                .collect()
+        },
+    }
+}
+
+/// Partitions `colors` by the vertices of `set` in each color.
+pub fn colors_by_vertices(
+    sync_graph: &SymbSyncGraph,
+    set: &GraphColoredVertices,
+    colors: &GraphColors,
+) -> Vec<(GraphColors, GraphVertices)> {
+    let mut classes = Vec::new();
+    let mut remaining = colors.clone();
+    while !remaining.is_empty() {
+        let vertices = set
+            .intersect_colors(&remaining.pick_singleton())
+            .vertices();
+        let same = sync_graph.unit_colored_vertices()
+            .intersect_vertices(&vertices)
+            .intersect_colors(&remaining);
+        let set = set.intersect_colors(&remaining);
+        let different = set.minus(&same).union(&same.minus(&set)).colors();
+        let class = remaining.minus(&different);
+        remaining = remaining.minus(&class);
+        classes.push((class, vertices));
+    }
+    classes
+}
+
+
+/*******************************************************************************
+ * Cost-complexity pruning
+ * =======================
+ * A subtree is replaced by a leaf if it does not pay off -- the share of
+ * colors the leaf assigns a wrong label (all but its largest class)
+ * grows by at most `alpha` per each removed leaf.
+ ******************************************************************************/
+
+/// Prunes `tree` deciding `all_colors` minimizing
+/// `misassigned share + alpha * number of leaves`.
+pub fn prune<L: LeafLabel>(
+    tree: &DecisionTree<L>,
+    all_colors: &Bdd,
+    alpha: f64,
+) -> DecisionTree<L> {
+    let total = all_colors.cardinality();
+    prune_rec(tree, all_colors, alpha, total).0
+}
+
+/// Returns the pruned subtree, its color classes, cost and number of leaves.
+fn prune_rec<L: LeafLabel>(
+    tree: &DecisionTree<L>,
+    colors: &Bdd,
+    alpha: f64,
+    total: f64,
+) -> (DecisionTree<L>, Vec<(Bdd, L)>, f64, usize) {
+    let leaf_cost = |classes: &[(Bdd, L)]| {
+        let sizes = classes.iter()
+            .map(|(colors, _)| colors.cardinality())
+            .collect::<Vec<_>>();
+        let largest = sizes.iter().copied().fold(0.0, f64::max);
+        (sizes.iter().sum::<f64>() - largest) / total
+    };
+
+    match tree {
+        DecisionTree::Leaf(label) =>
+            (tree.clone(), vec![(colors.clone(), label.clone())], 0.0, 1),
+        DecisionTree::ImpureLeaf(classes) =>
+            (tree.clone(), classes.clone(), leaf_cost(classes), 1),
+        DecisionTree::Node(node) => {
+            let fix = node.get_fix();
+            let [low, high] = node.get_childs();
+            let (low, mut classes, low_cost, low_leaves) =
+                prune_rec(low, &colors.and_not(fix), alpha, total);
+            let (high, high_classes, high_cost, high_leaves) =
+                prune_rec(high, &colors.and(fix), alpha, total);
+
+            // Classes of both children merged by the label
+            for (class_colors, label) in high_classes {
+                match classes.iter_mut().find(|(_, l)| *l == label) {
+                    Some((colors, _)) => *colors = colors.or(&class_colors),
+                    None => classes.push((class_colors, label)),
+                }
+            }
+
+            let cost = low_cost + high_cost;
+            let leaves = low_leaves + high_leaves;
+            let cost_as_leaf = leaf_cost(&classes);
+            if cost_as_leaf + alpha <= cost + alpha * leaves as f64 {
+                let leaf = UnresolvedNode {
+                    colors: colors.clone(),
+                    classes: classes.clone(),
+                }.into_leaf();
+                (leaf, classes, cost_as_leaf, 1)
+            } else {
+                let node = DecisionNode {
+                    childs: [Box::new(low), Box::new(high)],
+                    ..node.clone()
+                };
+                (DecisionTree::Node(node), classes, cost, leaves)
+            }
+        },
+    }
 }
 
 
 /*******************************************************************************
  * Building decision trees along with running the ibmfa simulation.
  * ================================================================
- * ibmfa -> driver set -> choose one parameter fix -> make decision node ->
+ * ibmfa -> driver set with parameter fixes -> make decision node of all the
+ * parameter fixes, the driver set is the leaf of the fixed colors ->
  * -> restrict colors to a negation of the fix -> ibmfa -> ...
- * Does not work well. It depends on heuristics on possible parameter
- * fixes -- single fixes of one parameter are not sufficient, the minimization
- * greedy search favors fixing vertices rather than parameters (after running
- * the postprocessing reduction). Even with better heuristics (and thus
- * having many more parameter fixes -> time complexity grows), the reduction
- * may still remove all parameter fixes if the "simplest" driver set (e.i.
- * the first one found) is the one that works as well for other colors.
- * Those colors may have been fixed because the entropy gets low faster in them.
- * So it seems to be a good idea to use entropy minimization for finding
- * vertices fix (the original "driver-set" meaning) but not parameters fix.
+ * Single parameters are often not sufficient, so conjunctions of a few
+ * parameters are tried as a single fix as well. The reduction may remove all
+ * parameter fixes if the driver-set works for other colors too, so the last
+ * parameter fix is kept and the driver-set alone is tried in all the colors
+ * instead. Still, the splits follow the entropy of IBMFA rather than
+ * the exact partition, see `compare_trees`.
  ******************************************************************************/
 
+/// Finds a decision tree for the colors of `attr` splitting them by
+/// the parameter fixes of driver-sets, see `find_parameter_driver_set`.
 pub fn decision_tree(
     sync_graph: &SymbSyncGraph,
     iterations: usize,
     attr: (&GraphVertices, &GraphColors),
     reduced: bool,
+    max_conjunction: usize,
 ) -> DecisionTree {
-    let (pbn_fix, _) = find_driver_set(
-        sync_graph, iterations, reduced, Some(attr), false, false);
-    decision_tree_recursive(sync_graph, iterations, attr, pbn_fix, reduced)
+    decision_tree_counted(
+        sync_graph, iterations, attr, reduced, max_conjunction, &mut 0)
 }
 
-fn decision_tree_recursive(
+/// As `decision_tree`, adding the IBMFA simulations run to `runs`.
+fn decision_tree_counted(
     sync_graph: &SymbSyncGraph,
     iterations: usize,
     attr: (&GraphVertices, &GraphColors),
-    mut pbn_fix: PBNFix,
     reduced: bool,
+    max_conjunction: usize,
+    runs: &mut usize,
 ) -> DecisionTree {
+    let (pbn_fix, _) = find_parameter_driver_set_counted(
+        sync_graph, iterations, reduced, attr, max_conjunction, runs, false);
+    let driver_set = pbn_fix.get_driver_set();
     if pbn_fix.get_parameter_fixes().is_empty() {
-        return DecisionTree::Leaf(pbn_fix.get_driver_set().clone());
+        return DecisionTree::Leaf(driver_set.clone());
     }
 
-    let mut pbn_fix_copy = pbn_fix.clone();
-    pbn_fix_copy.clear_colors_fix();
+    // The driver-set may work in all the colors
+    let mut vertex_fix = PBNFix::new(attr.1.as_bdd().clone());
+    for (&var_id, &value) in driver_set {
+        vertex_fix.insert(&UnitFix::Vertex(UnitVertexFix { var_id, value }));
+    }
+    *runs += 1;
+    let (entropy, _, _) = ibmfa_entropy(sync_graph, &vertex_fix, iterations,
+        false, None, None::<fn(&[f32])>, None, false);
+    if entropy == 0.0 {
+        if reduced {
+            vertex_fix = reduce_driver_set_counted(
+                vertex_fix, sync_graph, iterations, None, false, runs, false);
+        }
+        return DecisionTree::Leaf(vertex_fix.get_driver_set().clone());
+    }
 
-    let available_fixings = pbn_fix.get_parameter_fixes()
-        .iter()
-        .map(|fix| UnitFix::Parameter(fix.clone()))
+    let mut values = pbn_fix.get_parameter_fixes().iter()
+        .map(|fix| (fix.bdd_var, fix.value))
         .collect::<Vec<_>>();
-    let unit_fix = minimize_entropy(
-        &sync_graph, iterations, &mut pbn_fix_copy,
-        &available_fixings, None, false
-    ).map(|(unit_fix, _, _)| unit_fix).unwrap();
-
-    let UnitParameterFix { bdd_var, value } = match unit_fix {
-        UnitFix::Parameter(fix) => fix.clone(),
-        UnitFix::Vertex(_) => panic!("Expected parameter"),
+    values.sort();
+    let predicate = SplitPredicate::Parameters(
+        BddPartialValuation::from_values(&values));
+    let color_fix = predicate.colors(sync_graph);
+
+    let low_colors = attr.1.copy(attr.1.as_bdd().and_not(&color_fix));
+    let low = decision_tree_counted(sync_graph, iterations,
+        (attr.0, &low_colors), reduced, max_conjunction, runs);
+    if let DecisionTree::Leaf(low_driver_set) = &low {
+        if low_driver_set == driver_set {
+            return low;
+        }
+    }
+
+    DecisionTree::Node(DecisionNode {
+        childs: [
+            Box::new(low),
+            Box::new(DecisionTree::Leaf(driver_set.clone())),
+        ],
+        color_fix,
+        predicates: vec![(predicate, true)],
+    })
+}
+
+
+/*******************************************************************************
+ * Comparing the strategies
+ * ========================
+ * A tree of the driver-sets partition (`decision_tree_from_partition`)
+ * against the one built along IBMFA (`decision_tree`). The latter runs no
+ * exact verification, so its leaves are checked by `controlled_colors`.
+ ******************************************************************************/
+
+/// Statistics of a decision tree of driver-sets.
+#[derive(Clone, Debug)]
+pub struct TreeStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize,
+    /// Number of IBMFA simulations run to build the tree.
+    pub ibmfa_runs: usize,
+    pub seconds: f64,
+    /// Share of the colors whose leaf driver-set controls the network
+    /// to the attractor, see `controlled_colors`.
+    pub controlled_share: f64,
+}
+
+/// Builds the trees for `attr` by both strategies.
+///
+/// Returns the tree of the driver-sets partition and the tree built along
+/// IBMFA, each with its statistics.
+///
+/// * `max_conjunction` - As in `split_candidates` and
+///     `find_parameter_driver_set`.
+pub fn compare_trees(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    attr: (&GraphVertices, &GraphColors),
+    reduced: bool,
+    max_conjunction: usize,
+) -> [(DecisionTree, TreeStats); 2] {
+    let build = |strategy: &dyn Fn(&mut usize) -> DecisionTree| {
+        let mut runs = 0;
+        let start = Instant::now();
+        let tree = strategy(&mut runs);
+        let seconds = start.elapsed().as_secs_f64();
+        let stats = tree_stats(sync_graph, &tree, attr, runs, seconds);
+        (tree, stats)
     };
 
-    pbn_fix.remove(unit_fix);
-
-    let subtree_value = Box::new(decision_tree_recursive(
-            &sync_graph,
-            iterations,
-            (&attr.0,
-             &attr.1.copy(attr.1.as_bdd().var_select(bdd_var, value))),
-            pbn_fix,
-            reduced
-    ));
-
-    let colors_neg_value = 
-        attr.1.copy(attr.1.as_bdd().var_select(bdd_var, !value));
-    let attr_neg_value = (attr.0, &colors_neg_value);
-
-    let (pbn_fix, _) = find_driver_set(
-        &sync_graph,
-        iterations,
-        reduced,
-        Some(attr_neg_value),
-        false,
-        false
-    );
-
-    let subtree_neg_value = Box::new(decision_tree_recursive(
-            &sync_graph,
-            iterations,
-            attr_neg_value,
-            pbn_fix,
-            reduced,
-    ));
-
-    let (low, high) =
-        if value { (subtree_neg_value, subtree_value) }
-        else     { (subtree_value, subtree_neg_value) };
-
-    let color_fix = sync_graph.symbolic_context()
-        .bdd_variable_set().mk_var(bdd_var);
-
-    DecisionTree::Node(DecisionNode { childs: [low, high], color_fix })
+    [
+        build(&|runs| {
+            let driver_sets = colors_partition_counted(
+                sync_graph, iterations, reduced, attr, runs, false);
+            decision_tree_from_partition(sync_graph, attr.1.as_bdd(),
+                &driver_sets, max_conjunction, &TreeLimits::default())
+        }),
+        build(&|runs| decision_tree_counted(
+            sync_graph, iterations, attr, reduced, max_conjunction, runs)),
+    ]
+}
+
+fn tree_stats(
+    sync_graph: &SymbSyncGraph,
+    tree: &DecisionTree,
+    attr: (&GraphVertices, &GraphColors),
+    ibmfa_runs: usize,
+    seconds: f64,
+) -> TreeStats {
+    let controlled = tree.leaf_classes(attr.1.as_bdd()).iter()
+        .map(|(colors, driver_set)| controlled_colors(
+                sync_graph, driver_set, attr.0, &attr.1.copy(colors.clone()))
+            .approx_cardinality())
+        .sum::<f64>();
+    TreeStats {
+        nodes: tree.num_nodes(),
+        leaves: tree.num_leaves(),
+        depth: tree.depth(),
+        ibmfa_runs,
+        seconds,
+        controlled_share: controlled / attr.1.approx_cardinality(),
+    }
 }
@@ -0,0 +1,246 @@
+use biodivine_lib_bdd::{Bdd, BddPartialValuation};
+use biodivine_lib_bdd::boolean_expression::BooleanExpression;
+use biodivine_lib_param_bn::VariableId;
+use biodivine_lib_param_bn::symbolic_async_graph::
+    {GraphColors, SymbolicContext};
+
+use json::{JsonValue, object};
+
+use crate::symbolic_sync_graph::SymbSyncGraph;
+use crate::utils::bdd_to_str;
+use super::{DecisionNode, DecisionTree, LeafLabel, SplitPredicate};
+
+
+/*******************************************************************************
+ * Serialization of decision trees
+ * ===============================
+ * Predicates refer to the model by the names of variables and parameters,
+ * their colors are recomputed when a tree is loaded. The colors decided by
+ * the tree and the classes of impure leaves are stored as bdds, so a tree
+ * can be loaded only with the same model.
+ ******************************************************************************/
+
+/// Identifies a serialized decision tree.
+pub const TREE_FORMAT: &str = "pbn_ibmfa-decision-tree";
+/// Increased with every incompatible change of the format.
+pub const TREE_FORMAT_VERSION: u32 = 1;
+
+/// Serializes `predicate` with its `value` in a decision node.
+pub fn predicate_to_json(
+    predicate: &SplitPredicate,
+    value: bool,
+    sync_graph: &SymbSyncGraph,
+) -> JsonValue {
+    let context = sync_graph.symbolic_context();
+    let var_name = |var_id: &VariableId|
+        sync_graph.as_network().get_variable_name(*var_id).clone();
+    match predicate {
+        SplitPredicate::Parameters(valuation) => object!{
+            type: "parameters",
+            literals: valuation.to_values().iter()
+                .map(|&(bdd_var, value)| object!{
+                    parameter: context.bdd_variable_set().name_of(bdd_var),
+                    value: value,
+                })
+                .collect::<json::Array>(),
+            value: value,
+        },
+        SplitPredicate::Function(var_id, function) => object!{
+            type: "function",
+            variable: var_name(var_id),
+            function: bdd_to_str(function, context),
+            value: value,
+        },
+        SplitPredicate::Essential(regulator, target) => object!{
+            type: "essential",
+            regulator: var_name(regulator),
+            target: var_name(target),
+            value: value,
+        },
+    }
+}
+
+/// Inverse of `predicate_to_json`.
+pub fn predicate_from_json(json: &JsonValue, sync_graph: &SymbSyncGraph)
+-> Result<(SplitPredicate, bool), String> {
+    let context = sync_graph.symbolic_context();
+    let bdd_var_set = context.bdd_variable_set();
+    let predicate = match get_str(json, "type")? {
+        "parameters" => {
+            let values = json["literals"].members()
+                .map(|literal| {
+                    let name = get_str(literal, "parameter")?;
+                    let bdd_var = bdd_var_set.var_by_name(name)
+                        .filter(|bdd_var|
+                            context.parameter_variables().contains(bdd_var))
+                        .ok_or(format!("The model does not contain \
+                            parameter '{name}'."))?;
+                    Ok((bdd_var, get_bool(literal, "value")?))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            SplitPredicate::Parameters(
+                BddPartialValuation::from_values(&values))
+        },
+        "function" => {
+            let var_id = find_variable(get_str(json, "variable")?, sync_graph)?;
+            let function_str = get_str(json, "function")?;
+            let function = BooleanExpression::try_from(function_str).ok()
+                .and_then(|expression|
+                    bdd_var_set.safe_eval_expression(&expression))
+                .filter(|function| function.support_set().iter()
+                    .all(|bdd_var| context.state_variables().contains(bdd_var)))
+                .ok_or(format!("Invalid update function '{function_str}'."))?;
+            SplitPredicate::Function(var_id, function)
+        },
+        "essential" => SplitPredicate::Essential(
+            find_variable(get_str(json, "regulator")?, sync_graph)?,
+            find_variable(get_str(json, "target")?, sync_graph)?),
+        predicate_type =>
+            return Err(format!("Unknown predicate type '{predicate_type}'.")),
+    };
+    Ok((predicate, get_bool(json, "value")?))
+}
+
+/// Serializes `tree` deciding `colors` of `sync_graph`.
+///
+/// * `label_to_json` - Serializes the leaf labels, e.g. `driver_set_to_json`.
+pub fn tree_to_json<L: LeafLabel>(
+    tree: &DecisionTree<L>,
+    colors: &GraphColors,
+    sync_graph: &SymbSyncGraph,
+    label_to_json: &dyn Fn(&L) -> JsonValue,
+) -> JsonValue {
+    object!{
+        format: TREE_FORMAT,
+        version: TREE_FORMAT_VERSION,
+        bdd_variables: sync_graph.symbolic_context()
+            .bdd_variable_set().num_vars(),
+        colors: colors.as_bdd().to_string(),
+        tree: node_to_json(tree, sync_graph, label_to_json),
+    }
+}
+
+/// Inverse of `tree_to_json`.
+///
+/// Returns the tree with the colors it decides.
+pub fn tree_from_json<L: LeafLabel>(
+    json: &JsonValue,
+    sync_graph: &SymbSyncGraph,
+    label_from_json: &dyn Fn(&JsonValue) -> Result<L, String>,
+) -> Result<(DecisionTree<L>, GraphColors), String> {
+    if json["format"].as_str() != Some(TREE_FORMAT) {
+        return Err("Not a decision tree.".into());
+    }
+    if json["version"].as_u32() != Some(TREE_FORMAT_VERSION) {
+        return Err(format!("Unsupported version '{}' of the decision tree, \
+            expected '{TREE_FORMAT_VERSION}'.", json["version"]));
+    }
+    let context = sync_graph.symbolic_context();
+    if json["bdd_variables"].as_u16()
+            != Some(context.bdd_variable_set().num_vars()) {
+        return Err("The decision tree belongs to a different model.".into());
+    }
+    let colors = bdd_from_json(&json["colors"], context)?;
+    let tree = node_from_json(&json["tree"], sync_graph, label_from_json)?;
+    Ok((tree, GraphColors::new(colors, context)))
+}
+
+fn node_to_json<L: LeafLabel>(
+    tree: &DecisionTree<L>,
+    sync_graph: &SymbSyncGraph,
+    label_to_json: &dyn Fn(&L) -> JsonValue,
+) -> JsonValue {
+    match tree {
+        DecisionTree::Node(node) => object!{
+            split: node.predicates.iter()
+                .map(|(predicate, value)|
+                    predicate_to_json(predicate, *value, sync_graph))
+                .collect::<json::Array>(),
+            // Colors not satisfying the split
+            low: node_to_json(&node.childs[0], sync_graph, label_to_json),
+            high: node_to_json(&node.childs[1], sync_graph, label_to_json),
+        },
+        DecisionTree::Leaf(label) => object!{ label: label_to_json(label) },
+        DecisionTree::ImpureLeaf(labels) => object!{
+            classes: labels.iter()
+                .map(|(colors, label)| object!{
+                    colors: colors.to_string(),
+                    label: label_to_json(label),
+                })
+                .collect::<json::Array>(),
+        },
+    }
+}
+
+fn node_from_json<L: LeafLabel>(
+    json: &JsonValue,
+    sync_graph: &SymbSyncGraph,
+    label_from_json: &dyn Fn(&JsonValue) -> Result<L, String>,
+) -> Result<DecisionTree<L>, String> {
+    let context = sync_graph.symbolic_context();
+    if json.has_key("split") {
+        let predicates = json["split"].members()
+            .map(|predicate| predicate_from_json(predicate, sync_graph))
+            .collect::<Result<Vec<_>, String>>()?;
+        if predicates.is_empty() {
+            return Err("A decision node without predicates.".into());
+        }
+        let color_fix = predicates.iter()
+            .fold(context.mk_constant(true), |acc, (predicate, value)| {
+                let colors = predicate.colors(sync_graph);
+                if *value { acc.and(&colors) } else { acc.and_not(&colors) }
+            });
+        Ok(DecisionTree::Node(DecisionNode {
+            childs: [
+                Box::new(node_from_json(
+                    &json["low"], sync_graph, label_from_json)?),
+                Box::new(node_from_json(
+                    &json["high"], sync_graph, label_from_json)?),
+            ],
+            color_fix,
+            predicates,
+        }))
+    } else if json.has_key("label") {
+        Ok(DecisionTree::Leaf(label_from_json(&json["label"])?))
+    } else if json.has_key("classes") {
+        json["classes"].members()
+            .map(|class| Ok((
+                bdd_from_json(&class["colors"], context)?,
+                label_from_json(&class["label"])?,
+            )))
+            .collect::<Result<Vec<_>, String>>()
+            .map(DecisionTree::ImpureLeaf)
+    } else {
+        Err("Expected a decision node or a leaf.".into())
+    }
+}
+
+fn bdd_from_json(json: &JsonValue, context: &SymbolicContext)
+-> Result<Bdd, String> {
+    let bdd_str = json.as_str().ok_or("Expected colors.")?;
+    // `Bdd::read_as_string` expects three items in each node
+    if !bdd_str.split('|')
+            .filter(|node_str| !node_str.is_empty())
+            .all(|node_str| node_str.split(',').count() == 3) {
+        return Err(format!("Invalid colors '{bdd_str}'."));
+    }
+    Bdd::read_as_string(&mut bdd_str.as_bytes())
+        .ok()
+        .filter(|bdd|
+            bdd.num_vars() == context.bdd_variable_set().num_vars())
+        .ok_or(format!("Invalid colors '{bdd_str}'."))
+}
+
+fn find_variable(name: &str, sync_graph: &SymbSyncGraph)
+-> Result<VariableId, String> {
+    sync_graph.as_network().as_graph().find_variable(name)
+        .ok_or(format!("The model does not contain variable '{name}'."))
+}
+
+fn get_str<'a>(json: &'a JsonValue, key: &str) -> Result<&'a str, String> {
+    json[key].as_str().ok_or(format!("Expected a string '{key}'."))
+}
+
+fn get_bool(json: &JsonValue, key: &str) -> Result<bool, String> {
+    json[key].as_bool().ok_or(format!("Expected a boolean '{key}'."))
+}
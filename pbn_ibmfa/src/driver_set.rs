@@ -5,14 +5,27 @@ use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, GraphVertices};
 use biodivine_lib_bdd::Bdd;
 
 use crate::symbolic_sync_graph::{SymbSyncGraph, PUpdateFunExplicit};
-use crate::ibmfa_computations::{minimize_entropy, ibmfa_entropy};
+use crate::ibmfa_computations::{minimize_entropy_counted, ibmfa_entropy};
+use crate::utils::{combinations, variations_with_replacement};
 use fixes::{UnitVertexFix, UnitParameterFix, DriverSet};
-pub use fixes::{PBNFix, UnitFix, driver_set_to_str};
+pub use fixes::{PBNFix, UnitFix, driver_set_to_str, driver_set_to_json,
+    driver_set_from_json};
 
 
 pub mod fixes;
-
-
+pub mod pulse;
+pub mod phenotype;
+pub mod robust;
+
+
+/// Partitions the colors of `attr` by driver-sets controlling them.
+///
+/// Instead of running IBMFA for every single color, a driver-set is found
+/// for a whole set of colors and the colors it covers are verified exactly
+/// (see `covered_colors`). Only the uncovered colors are split in halves
+/// and processed again, first trying the driver-sets found in the meantime.
+/// A single color whose driver-set does not verify gets a separate entry,
+/// never merged with the verified ones nor tried on other colors.
 pub fn colors_partition(
     sync_graph: &SymbSyncGraph,
     iterations: usize,
@@ -20,30 +33,79 @@ pub fn colors_partition(
     attr: (&GraphVertices, &GraphColors),
     verbose: bool,
 ) -> Vec<(Bdd, DriverSet)> {
-    let mut driver_sets: Vec<(Bdd, DriverSet)> = Vec::new();
+    colors_partition_counted(
+        sync_graph, iterations, reduced, attr, &mut 0, verbose)
+}
 
-    let mut remaining_colors = attr.1.clone();
-    while !remaining_colors.is_empty() {
-        let color = remaining_colors.pick_singleton();
-        remaining_colors = remaining_colors.minus(&color);
+/// As `colors_partition`, adding the IBMFA simulations run to `runs`.
+pub(crate) fn colors_partition_counted(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    attr: (&GraphVertices, &GraphColors),
+    runs: &mut usize,
+    verbose: bool,
+) -> Vec<(Bdd, DriverSet)> {
+    let mut driver_sets: Vec<(Bdd, DriverSet)> = Vec::new();
+    let mut unverified: Vec<(Bdd, DriverSet)> = Vec::new();
+
+    // Colors with the number of driver-sets already tried on them
+    let mut unresolved = vec![(attr.1.clone(), 0)];
+    while let Some((mut colors, tried)) = unresolved.pop() {
+        // Colors covered by driver-sets found in the meantime
+        for (dset_colors, driver_set) in driver_sets[tried..].iter_mut() {
+            let covered = covered_colors(
+                sync_graph, driver_set, attr.0, &colors, reduced);
+            *dset_colors = dset_colors.or(covered.as_bdd());
+            colors = colors.minus(&covered);
+        }
+        if colors.is_empty() {
+            continue;
+        }
 
-        let (pbn_fix, _) = find_driver_set(
-            sync_graph, iterations, reduced, Some((attr.0, &color)),
-            true, verbose);
+        let (pbn_fix, _) = find_driver_set_counted(
+            sync_graph, iterations, reduced, Some((attr.0, &colors)),
+            true, runs, verbose);
         assert!(pbn_fix.get_parameter_fixes().is_empty());
 
         let driver_set = pbn_fix.get_driver_set();
-        if let Some(i) = driver_sets.iter()
-                .position(|(_, driver)| *driver == *driver_set) {
-            driver_sets[i].0 = driver_sets[i].0.or(color.as_bdd());
-        } else {
-            driver_sets.push((color.into_bdd(), driver_set.clone()));
+        let covered = covered_colors(
+            sync_graph, driver_set, attr.0, &colors, reduced);
+
+        if verbose {
+            println!("{} covers {} of {} colors",
+                driver_set_to_str(driver_set, sync_graph.symbolic_context()),
+                covered.approx_cardinality(), colors.approx_cardinality());
+        }
+
+        if !covered.is_empty() {
+            if let Some(i) = driver_sets.iter()
+                    .position(|(_, driver)| *driver == *driver_set) {
+                driver_sets[i].0 = driver_sets[i].0.or(covered.as_bdd());
+            } else {
+                driver_sets.push((covered.as_bdd().clone(),
+                                  driver_set.clone()));
+            }
+        }
+
+        let uncovered = colors.minus(&covered);
+        let tried = driver_sets.len();
+        if colors.is_singleton() && !uncovered.is_empty() {
+            unverified.push((uncovered.into_bdd(), driver_set.clone()));
+        } else if uncovered.is_singleton() {
+            unresolved.push((uncovered, tried));
+        } else if !uncovered.is_empty() {
+            unresolved.extend(split_colors(sync_graph, &uncovered)
+                .map(|half| (half, tried)));
         }
     }
 
+    driver_sets.extend(unverified);
     driver_sets
 }
 
+/// Finds a driver-set for the colors of `attr_opt` (all the colors
+/// by default) by a greedy search minimizing the entropy of IBMFA.
 pub fn find_driver_set(
     sync_graph: &SymbSyncGraph,
     iterations: usize,
@@ -51,6 +113,20 @@ pub fn find_driver_set(
     attr_opt: Option<(&GraphVertices, &GraphColors)>,
     fix_only_vertices: bool,
     verbose: bool,
+) -> (PBNFix, Vec<f32>) {
+    find_driver_set_counted(sync_graph, iterations, reduced, attr_opt,
+        fix_only_vertices, &mut 0, verbose)
+}
+
+/// As `find_driver_set`, adding the IBMFA simulations run to `runs`.
+pub(crate) fn find_driver_set_counted(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    attr_opt: Option<(&GraphVertices, &GraphColors)>,
+    fix_only_vertices: bool,
+    runs: &mut usize,
+    verbose: bool,
 ) -> (PBNFix, Vec<f32>) {
     // Colors that will be explored
     let colors = match attr_opt {
@@ -71,86 +147,187 @@ pub fn find_driver_set(
     // Build up the driver set in a greedy optimization search
     let (mut pbn_fix, probs) = build_driver_set(
         sync_graph, iterations, colors, attr_opt.map(|tup| tup.0),
-        explicit_pupdate_funs_opt, verbose);
+        explicit_pupdate_funs_opt, runs, verbose);
 
     // Exclude unnecessary fixes
     if reduced {
-        pbn_fix = reduce_driver_set(pbn_fix, sync_graph, iterations,
-            explicit_pupdate_funs_opt, verbose);
+        pbn_fix = reduce_driver_set_counted(pbn_fix, sync_graph, iterations,
+            explicit_pupdate_funs_opt, false, runs, verbose);
+    }
+
+    (pbn_fix, probs)
+}
+
+/// As `find_driver_set` with parameter fixes allowed, restricting
+/// the colors where the driver-set works.
+///
+/// Besides single parameters, conjunctions of up to `max_conjunction`
+/// parameter literals are tried as a single fix, as a single parameter
+/// often does not decrease the entropy on its own. The reduction never
+/// excludes the last parameter fix, see `reduce_driver_set_counted`.
+pub fn find_parameter_driver_set(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    attr: (&GraphVertices, &GraphColors),
+    max_conjunction: usize,
+    verbose: bool,
+) -> (PBNFix, Vec<f32>) {
+    find_parameter_driver_set_counted(sync_graph, iterations, reduced, attr,
+        max_conjunction, &mut 0, verbose)
+}
+
+/// As `find_parameter_driver_set`, adding the IBMFA simulations run
+/// to `runs`.
+pub(crate) fn find_parameter_driver_set_counted(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    attr: (&GraphVertices, &GraphColors),
+    max_conjunction: usize,
+    runs: &mut usize,
+    verbose: bool,
+) -> (PBNFix, Vec<f32>) {
+    let (mut available_fixes, mut pbn_fix) = prepare_fixes(
+        sync_graph, Some(attr.0), attr.1.as_bdd().clone(), false);
+    available_fixes.extend(filter_fixes(
+        &compound_parameter_fixes(attr.1, max_conjunction), &pbn_fix));
+
+    let probs = greedy_entropy_fixes(sync_graph, iterations, available_fixes,
+        &mut pbn_fix, None, runs, verbose);
+
+    if reduced {
+        pbn_fix = reduce_driver_set_counted(
+            pbn_fix, sync_graph, iterations, None, true, runs, verbose);
     }
 
     (pbn_fix, probs)
 }
 
+/// Conjunctions of 2 up to `max_conjunction` literals of the parameters
+/// `colors` depend on.
+fn compound_parameter_fixes(colors: &GraphColors, max_conjunction: usize)
+-> Vec<UnitFix> {
+    let pars = colors.as_bdd().support_set().into_iter().collect::<Vec<_>>();
+    (2..=max_conjunction)
+        .flat_map(|size| {
+            let all_values = variations_with_replacement(&[false, true], size);
+            combinations(&pars, size).into_iter()
+                .flat_map(move |conj_pars| all_values.clone().into_iter()
+                    .map(move |values| UnitFix::Parameters(conj_pars.iter()
+                        .zip(values)
+                        .map(|(&bdd_var, value)|
+                            UnitParameterFix { bdd_var, value })
+                        .collect())))
+        })
+        .collect()
+}
+
 fn build_driver_set(
     sync_graph: &SymbSyncGraph,
     iterations: usize,
     colors: Bdd,
     attr_opt: Option<&GraphVertices>,
     explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    runs: &mut usize,
     verbose: bool
 ) -> (PBNFix, Vec<f32>) {
-    let (mut available_fixes, mut pbn_fix) = prepare_fixes(
-        &sync_graph, attr_opt, colors, explicit_pupdate_funs_opt.is_some());
+    let (available_fixes, mut pbn_fix) = prepare_fixes(
+        sync_graph, attr_opt, colors, explicit_pupdate_funs_opt.is_some());
+    let probs = greedy_entropy_fixes(sync_graph, iterations, available_fixes,
+        &mut pbn_fix, explicit_pupdate_funs_opt, runs, verbose);
+    (pbn_fix, probs)
+}
 
+/// Greedily adds fixes from `available_fixes` to `pbn_fix`, each time the one
+/// minimizing the entropy of IBMFA, until it reaches zero or no fix is left.
+///
+/// Returns the final probabilities.
+fn greedy_entropy_fixes(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    mut available_fixes: Vec<UnitFix>,
+    pbn_fix: &mut PBNFix,
+    explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    runs: &mut usize,
+    verbose: bool
+) -> Vec<f32> {
     let mut final_probs = Vec::new();
 
-    while available_fixes.len() > 0 {
+    while !available_fixes.is_empty() {
         if verbose {
             println!("======= {} ========", available_fixes.len());
         }
 
-        let (unit_fix, min_entropy, probs) = minimize_entropy(
-            &sync_graph, iterations, &mut pbn_fix, &available_fixes,
-            explicit_pupdate_funs_opt, verbose).unwrap();
+        let (unit_fix, min_entropy, probs) = minimize_entropy_counted(
+            sync_graph, iterations, pbn_fix, &available_fixes,
+            explicit_pupdate_funs_opt, runs, verbose).unwrap();
 
         pbn_fix.insert(unit_fix);
 
         if verbose {
             println!("Fixing {}, entropy:{min_entropy}",
-                unit_fix.to_str(sync_graph.symbolic_context()));
-            println!("{}", pbn_fix.to_str(sync_graph.symbolic_context()));
+                unit_fix.to_str(sync_graph));
+            println!("{}", pbn_fix.to_str(sync_graph));
         }
 
-        available_fixes = filter_fixes(&available_fixes, &pbn_fix);
+        available_fixes = filter_fixes(&available_fixes, pbn_fix);
         final_probs = probs;
 
         if min_entropy == 0.0 {
             break;
         }
     }
-    (pbn_fix, final_probs)
+    final_probs
 }
 
+/// Excludes fixes of `pbn_fix` as long as the entropy of IBMFA stays zero,
+/// each time the one converging the fastest.
 pub fn reduce_driver_set(
+    pbn_fix: PBNFix,
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    verbose: bool
+) -> PBNFix {
+    reduce_driver_set_counted(pbn_fix, sync_graph, iterations,
+        explicit_pupdate_funs_opt, false, &mut 0, verbose)
+}
+
+/// As `reduce_driver_set`.
+///
+/// * `keep_parameter_fix` - Never exclude the last parameter fix, so
+///     the colors stay restricted.
+/// * `runs` - Counts the IBMFA simulations, one per tried exclusion.
+pub(crate) fn reduce_driver_set_counted(
     mut pbn_fix: PBNFix,
     sync_graph: &SymbSyncGraph,
     iterations: usize,
     explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    keep_parameter_fix: bool,
+    runs: &mut usize,
     verbose: bool
 ) -> PBNFix {
-    let mut fixes = pbn_fix.get_driver_set()
-        .iter()
-        .map(|(&var_id, &value)|
-            UnitFix::Vertex(UnitVertexFix { var_id, value }))
-        .chain(pbn_fix.get_parameter_fixes()
-            .iter()
-            .map(|unit_par_fix| UnitFix::Parameter(unit_par_fix.clone())))
-        .collect::<Vec<_>>();
+    let mut fixes = pbn_fix.unit_fixes();
 
     loop {
         let mut to_remove = None;
         let mut to_remove_i = 0;
         let mut to_remove_conv_index = iterations + 1;
         for (i, unit_fix) in fixes.iter().enumerate() {
+            if keep_parameter_fix && matches!(unit_fix, UnitFix::Parameter(_))
+                    && pbn_fix.get_parameter_fixes().len() == 1 {
+                continue;
+            }
             if verbose {
                 println!("Try removing {}",
-                    unit_fix.to_str(&sync_graph.symbolic_context()));
+                    unit_fix.to_str(sync_graph));
             }
 
             pbn_fix.remove(unit_fix);
+            *runs += 1;
             let (ent, _, conv_index) = ibmfa_entropy(
-                &sync_graph, &pbn_fix, iterations, false,
+                sync_graph, &pbn_fix, iterations, false,
                 explicit_pupdate_funs_opt, None::<fn(&[f32])>, None, false);
             pbn_fix.insert(unit_fix);
 
@@ -169,8 +346,8 @@ pub fn reduce_driver_set(
             fixes.remove(to_remove_i);
             if verbose {
                 println!("Removing {}",
-                    to_remove.to_str(&sync_graph.symbolic_context()));
-                println!("{}", pbn_fix.to_str(sync_graph.symbolic_context()));
+                    to_remove.to_str(sync_graph));
+                println!("{}", pbn_fix.to_str(sync_graph));
             }
         } else {
             break;
@@ -180,6 +357,120 @@ pub fn reduce_driver_set(
     pbn_fix
 }
 
+/// Colors of `colors` in which `driver_set` controls `sync_graph` to `target`,
+/// i.e. all the attractors of the fixed network lie in `target`.
+pub fn controlled_colors(
+    sync_graph: &SymbSyncGraph,
+    driver_set: &DriverSet,
+    target: &GraphVertices,
+    colors: &GraphColors,
+) -> GraphColors {
+    let set = sync_graph.unit_colored_vertices().intersect_colors(colors);
+    sync_graph.fixed_by(driver_set)
+        .attractors_in(&set)
+        .iter()
+        .fold(colors.clone(), |acc, attr|
+            acc.minus(&attr.minus_vertices(target).colors()))
+}
+
+/// Greedily adds fixes from `available_fixes` to `pbn_fix`, each time the one
+/// minimizing `objective`, until it reaches zero or no fix is left.
+///
+/// Returns the final value of `objective`.
+fn greedy_fixes(
+    sync_graph: &SymbSyncGraph,
+    mut available_fixes: Vec<UnitFix>,
+    pbn_fix: &mut PBNFix,
+    objective: impl Fn(&PBNFix) -> f32,
+    verbose: bool,
+) -> f32 {
+    let mut min_objective = objective(pbn_fix);
+
+    while min_objective > 0.0 && !available_fixes.is_empty() {
+        let (unit_fix, value) = available_fixes.iter()
+            .map(|unit_fix| {
+                pbn_fix.insert(unit_fix);
+                let value = objective(pbn_fix);
+                pbn_fix.remove(unit_fix);
+                (unit_fix.clone(), value)
+            })
+            .min_by(|(_, v1), (_, v2)| v1.total_cmp(v2))
+            .unwrap();
+
+        pbn_fix.insert(&unit_fix);
+        if verbose {
+            println!("Fixing {}, objective:{value}",
+                unit_fix.to_str(sync_graph));
+        }
+
+        available_fixes = filter_fixes(&available_fixes, pbn_fix);
+        min_objective = value;
+    }
+    min_objective
+}
+
+/// Removes fixes from `pbn_fix` as long as `objective` stays zero.
+fn greedy_reduce(
+    sync_graph: &SymbSyncGraph,
+    pbn_fix: &mut PBNFix,
+    objective: impl Fn(&PBNFix) -> f32,
+    verbose: bool,
+) {
+    loop {
+        let to_remove = pbn_fix.unit_fixes()
+            .into_iter()
+            .find(|unit_fix| {
+                let mut reduced_fix = pbn_fix.clone();
+                reduced_fix.remove(unit_fix);
+                objective(&reduced_fix) == 0.0
+            });
+        match to_remove {
+            Some(unit_fix) => {
+                if verbose {
+                    println!("Removing {}",
+                        unit_fix.to_str(sync_graph));
+                }
+                pbn_fix.remove(&unit_fix);
+            },
+            None => break,
+        }
+    }
+}
+
+/// Colors of `colors` where `driver_set` controls `sync_graph` to `target`
+/// and, if `reduced`, none of its fixes can be excluded.
+fn covered_colors(
+    sync_graph: &SymbSyncGraph,
+    driver_set: &DriverSet,
+    target: &GraphVertices,
+    colors: &GraphColors,
+    reduced: bool,
+) -> GraphColors {
+    let controlled = controlled_colors(sync_graph, driver_set, target, colors);
+    if !reduced {
+        return controlled;
+    }
+    driver_set.keys().fold(controlled, |acc, var_id| {
+        if acc.is_empty() {
+            return acc;
+        }
+        let mut smaller = driver_set.clone();
+        smaller.remove(var_id);
+        acc.minus(&controlled_colors(sync_graph, &smaller, target, &acc))
+    })
+}
+
+/// Splits `colors` (more than one) in two non-empty halves by the value
+/// of a parameter variable.
+fn split_colors(sync_graph: &SymbSyncGraph, colors: &GraphColors)
+-> [GraphColors; 2] {
+    sync_graph.symbolic_context().parameter_variables().iter()
+        .map(|bdd_var| [false, true].map(|value|
+            colors.copy(colors.as_bdd().var_select(*bdd_var, value))))
+        .find(|halves| halves.iter().all(|half| !half.is_empty()))
+        .unwrap()
+}
+
 fn prepare_fixes(
     sync_graph: &SymbSyncGraph,
     attr_opt: Option<&GraphVertices>,
@@ -228,6 +519,15 @@ fn filter_fixes(fixes: &[UnitFix], pbn_fix: &PBNFix) -> Vec<UnitFix> {
                 !after.is_false() && after != before
                     && color_fixes.insert(after)
             },
+            // Each parameter restricts the colors, so none is fixed yet
+            UnitFix::Parameters(fixes) => {
+                let before = pbn_fix.colors();
+                let after = fixes.iter().fold(before.clone(), |acc, fix|
+                    acc.var_select(fix.bdd_var, fix.value));
+                !after.is_false() && fixes.iter().all(|fix|
+                        before.var_select(fix.bdd_var, fix.value) != before)
+                    && color_fixes.insert(after)
+            },
             UnitFix::Vertex(UnitVertexFix { var_id, .. }) =>
                 pbn_fix.get_vertex(*var_id).is_none()
         })
@@ -3,7 +3,10 @@ use std::collections::{BTreeMap, HashSet};
 use biodivine_lib_bdd::{Bdd, BddVariable};
 use biodivine_lib_param_bn::{VariableId,
     symbolic_async_graph::{SymbolicContext}};
+use json::{JsonValue, object};
 
+use crate::parameter_names::parameter_name;
+use crate::symbolic_sync_graph::SymbSyncGraph;
 use crate::utils::{bdd_to_str, bdd_var_to_str};
 
 
@@ -23,6 +26,8 @@ pub struct UnitParameterFix {
 pub enum UnitFix {
     Vertex(UnitVertexFix),
     Parameter(UnitParameterFix),
+    /// Conjunction of parameter fixes applied in a single step.
+    Parameters(Vec<UnitParameterFix>),
 }
 
 pub type DriverSet = BTreeMap<VariableId, bool>;
@@ -47,6 +52,38 @@ pub fn driver_set_to_str(
         .collect::<String>())
 }
 
+/// Serializes `driver_set` as a list of `{variable, value}` objects.
+pub fn driver_set_to_json(driver_set: &DriverSet, context: &SymbolicContext)
+-> JsonValue {
+    let bdd_var_set = context.bdd_variable_set();
+    JsonValue::Array(driver_set.iter()
+        .map(|(var_id, value)| object!{
+            variable: bdd_var_set.name_of(context.get_state_variable(*var_id)),
+            value: *value,
+        })
+        .collect::<json::Array>())
+}
+
+/// Inverse of `driver_set_to_json`.
+pub fn driver_set_from_json(json: &JsonValue, sync_graph: &SymbSyncGraph)
+-> Result<DriverSet, String> {
+    if !json.is_array() {
+        return Err("Expected a list of fixes.".into());
+    }
+    json.members()
+        .map(|fix| {
+            let name = fix["variable"].as_str()
+                .ok_or("Expected a string 'variable'.")?;
+            let var_id = sync_graph.as_network().as_graph()
+                .find_variable(name).ok_or(format!(
+                    "The model does not contain variable '{name}'."))?;
+            let value = fix["value"].as_bool()
+                .ok_or("Expected a boolean 'value'.")?;
+            Ok((var_id, value))
+        })
+        .collect()
+}
+
 impl UnitVertexFix {
     pub fn to_str(&self, context: &SymbolicContext) -> String {
         let bdd_var = context.get_state_variable(self.var_id);
@@ -57,18 +94,23 @@ impl UnitVertexFix {
 }
 
 impl UnitParameterFix {
-    pub fn to_str(&self, context: &SymbolicContext) -> String {
+    /// The fix with the readable name of the parameter, see `parameter_name`.
+    pub fn to_str(&self, sync_graph: &SymbSyncGraph) -> String {
         format!("{}={}",
-            bdd_var_to_str(self.bdd_var, &context),
+            parameter_name(self.bdd_var, sync_graph),
             if self.value { 1 } else { 0 })
     }
 }
 
 impl UnitFix {
-    pub fn to_str(&self, context: &SymbolicContext) -> String {
+    pub fn to_str(&self, sync_graph: &SymbSyncGraph) -> String {
         match self {
-            UnitFix::Vertex(fix) => fix.to_str(&context),
-            UnitFix::Parameter(fix) => fix.to_str(&context),
+            UnitFix::Vertex(fix) => fix.to_str(sync_graph.symbolic_context()),
+            UnitFix::Parameter(fix) => fix.to_str(sync_graph),
+            UnitFix::Parameters(fixes) => fixes.iter()
+                .map(|fix| fix.to_str(sync_graph))
+                .collect::<Vec<_>>()
+                .join(" & "),
         }
     }
 }
@@ -108,6 +150,22 @@ impl PBNFix {
         &self.driver_set
     }
 
+    /// Returns all the vertex fixes and parameter fixes.
+    pub fn unit_fixes(&self) -> Vec<UnitFix> {
+        self.driver_set.iter()
+            .map(|(&var_id, &value)|
+                UnitFix::Vertex(UnitVertexFix { var_id, value }))
+            .chain(self.parameter_fixes.iter()
+                .map(|unit_par_fix| UnitFix::Parameter(unit_par_fix.clone())))
+            .collect()
+    }
+
+    /// Returns the fix without its driver-set, only the colors
+    /// restriction remains.
+    pub fn released(&self) -> PBNFix {
+        PBNFix { driver_set: DriverSet::new(), ..self.clone() }
+    }
+
     pub fn insert(&mut self, fix: &UnitFix) {
         match fix {
             UnitFix::Vertex(fix) =>
@@ -119,6 +177,9 @@ impl PBNFix {
                     self.colors_fix.var_select(fix.bdd_var, fix.value);
                 self.parameter_fixes.insert(fix.clone());
             }
+            UnitFix::Parameters(fixes) => for fix in fixes {
+                self.insert(&UnitFix::Parameter(fix.clone()));
+            }
         }
     }
 
@@ -134,23 +195,26 @@ impl PBNFix {
                 }
                 self.colors_fix = self.colors_fix.var_project(fix.bdd_var);
             }
+            UnitFix::Parameters(fixes) => for fix in fixes {
+                self.remove(&UnitFix::Parameter(fix.clone()));
+            }
         }
     }
 
 
-    pub fn par_fixes_to_str(&self, context: &SymbolicContext) -> String {
+    pub fn par_fixes_to_str(&self, sync_graph: &SymbSyncGraph) -> String {
         format!("{{ {}}}", self.parameter_fixes.iter()
-            .map(|par_fix| format!("{} ", par_fix.to_str(&context)))
+            .map(|par_fix| format!("{} ", par_fix.to_str(sync_graph)))
             .collect::<String>())
     }
 
     pub fn colors_to_str(&self, context: &SymbolicContext) -> String {
-        bdd_to_str(&self.colors(), &context)
+        bdd_to_str(&self.colors(), context)
     }
 
-    pub fn to_str(&self, context: &SymbolicContext) -> String {
+    pub fn to_str(&self, sync_graph: &SymbSyncGraph) -> String {
         format!("Driver-set: {}\nParameter-fixes: {}",
-            driver_set_to_str(&self.driver_set, &context),
-            self.par_fixes_to_str(&context))
+            driver_set_to_str(&self.driver_set, sync_graph.symbolic_context()),
+            self.par_fixes_to_str(sync_graph))
     }
 }
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use biodivine_lib_param_bn::VariableId;
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, GraphVertices};
+
+use crate::symbolic_sync_graph::SymbSyncGraph;
+use crate::ibmfa_computations::ibmfa_entropy;
+use crate::utils::{combinations, variations_with_replacement};
+use super::{filter_fixes, greedy_fixes, greedy_reduce, PBNFix, UnitFix};
+use super::fixes::{DriverSet, UnitVertexFix};
+
+
+/*******************************************************************************
+ * Phenotypes
+ * ==========
+ * A phenotype is a partial state of the network, e.g. "Apoptosis=1"
+ * regardless of other variables. Driver-sets for a phenotype are searched
+ * and verified only on the phenotype variables.
+ ******************************************************************************/
+
+/// Partial valuation of network variables.
+pub type Phenotype = BTreeMap<VariableId, bool>;
+
+/// Vertices of `sync_graph` satisfying `phenotype`.
+pub fn phenotype_vertices(sync_graph: &SymbSyncGraph, phenotype: &Phenotype)
+-> GraphVertices {
+    let context = sync_graph.symbolic_context();
+    let values = phenotype.iter()
+        .map(|(&var_id, &value)| (context.get_state_variable(var_id), value))
+        .collect::<Vec<_>>();
+    GraphVertices::new(context.mk_constant(true).select(&values), context)
+}
+
+/// Mean difference of the probabilities of phenotype variables from their
+/// values in `phenotype`.
+pub fn phenotype_distance(probs: &[f32], phenotype: &Phenotype) -> f32 {
+    phenotype.iter()
+        .map(|(var_id, &value)| {
+            let prob = probs[var_id.to_index()];
+            if value { 1.0 - prob } else { prob }
+        })
+        .sum::<f32>() / phenotype.len().max(1) as f32
+}
+
+/// Finds a driver-set controlling `sync_graph` to `phenotype` in `colors`.
+///
+/// Vertex fixes are added greedily minimizing `phenotype_distance` after
+/// `iterations` steps of IBMFA. The phenotype variables themselves are
+/// never fixed.
+///
+/// Returns the fix and the final probabilities. The driver-set is not
+/// guaranteed to work if the distance is not zero, use `controlled_colors`
+/// with `phenotype_vertices` for an exact verification.
+pub fn find_phenotype_driver_set(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    phenotype: &Phenotype,
+    colors: &GraphColors,
+    verbose: bool,
+) -> (PBNFix, Vec<f32>) {
+    let explicit_pupdate_funs =
+        sync_graph.explicit_pupdate_functions(colors.as_bdd());
+
+    let simulate = |pbn_fix: &PBNFix| ibmfa_entropy(
+        sync_graph, pbn_fix, iterations, false,
+        Some(&explicit_pupdate_funs), None::<fn(&[f32])>, None, false).1;
+    let objective =
+        |pbn_fix: &PBNFix| phenotype_distance(&simulate(pbn_fix), phenotype);
+
+    let mut pbn_fix = PBNFix::new(colors.as_bdd().clone());
+    let available_fixes = sync_graph.as_network().variables()
+        .filter(|var_id| !phenotype.contains_key(var_id))
+        .flat_map(|var_id| [false, true].map(|value|
+            UnitFix::Vertex(UnitVertexFix { var_id, value })))
+        .collect::<Vec<_>>();
+    let available_fixes = filter_fixes(&available_fixes, &pbn_fix);
+
+    let min_distance = greedy_fixes(
+        sync_graph, available_fixes, &mut pbn_fix, objective, verbose);
+
+    if reduced && min_distance == 0.0 {
+        greedy_reduce(sync_graph, &mut pbn_fix, objective, verbose);
+    }
+
+    let probs = simulate(&pbn_fix);
+    (pbn_fix, probs)
+}
+
+/*******************************************************************************
+ * Avoiding a phenotype
+ * ====================
+ * "Escape" control -- fix the network such that no attractor satisfies
+ * an undesired phenotype in any color. Checked exactly on the symbolic graph.
+ ******************************************************************************/
+
+/// Colors of `colors` in which some attractor of `sync_graph` fixed by
+/// `driver_set` satisfies `phenotype`.
+pub fn phenotype_attractor_colors(
+    sync_graph: &SymbSyncGraph,
+    driver_set: &DriverSet,
+    phenotype: &Phenotype,
+    colors: &GraphColors,
+) -> GraphColors {
+    let fixed_graph = sync_graph.fixed_by(driver_set);
+    let vertices = phenotype_vertices(sync_graph, phenotype);
+    let set = fixed_graph.unit_colored_vertices().intersect_colors(colors);
+
+    // Fixed points are cheap, cyclic attractors are searched only
+    // in the remaining colors.
+    let colors = fixed_graph
+        .fixed_point_attractors_in(&set.intersect_vertices(&vertices))
+        .iter()
+        .fold(sync_graph.empty_colored_vertices().colors(),
+            |acc, attr| acc.union(&attr.colors()));
+
+    fixed_graph.attractors_in(&set.minus_colors(&colors))
+        .iter()
+        .fold(colors, |acc, attr|
+            acc.union(&attr.intersect_vertices(&vertices).colors()))
+}
+
+/// Finds the smallest driver-sets after which no attractor of `sync_graph`
+/// satisfies `phenotype` in `colors`.
+///
+/// All the driver-sets up to `max_size` fixes are tried by increasing size.
+/// The phenotype variables themselves are never fixed.
+///
+/// Returns the driver-sets with the colors where they fail. If no driver-set
+/// works in all the colors, those with the least failing colors are returned.
+pub fn avoiding_driver_sets(
+    sync_graph: &SymbSyncGraph,
+    phenotype: &Phenotype,
+    colors: &GraphColors,
+    max_size: usize,
+    verbose: bool,
+) -> Vec<(DriverSet, GraphColors)> {
+    let variables = sync_graph.as_network().variables()
+        .filter(|var_id| !phenotype.contains_key(var_id))
+        .collect::<Vec<_>>();
+
+    let mut best: Vec<(DriverSet, GraphColors)> = Vec::new();
+    let mut best_failing = f64::INFINITY;
+
+    for size in 0..=max_size.min(variables.len()) {
+        if verbose {
+            println!("======= {size} ========");
+        }
+        for fixed_vars in combinations(&variables, size) {
+            for values in variations_with_replacement(&[false, true], size) {
+                let driver_set = fixed_vars.iter().copied()
+                    .zip(values)
+                    .collect::<DriverSet>();
+                let failing = phenotype_attractor_colors(
+                    sync_graph, &driver_set, phenotype, colors);
+                let failing_num = failing.approx_cardinality();
+
+                if failing_num < best_failing {
+                    best.clear();
+                    best_failing = failing_num;
+                }
+                // Only the smallest driver-sets among the equally good ones
+                if failing_num == best_failing
+                        && best.iter().all(|(best_set, _)|
+                            best_set.len() == size) {
+                    best.push((driver_set, failing));
+                }
+            }
+        }
+        if best_failing == 0.0 {
+            break;
+        }
+    }
+    best
+}
@@ -0,0 +1,157 @@
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::
+    {GraphColoredVertices, GraphColors, GraphVertices};
+
+use crate::symbolic_sync_graph::{SymbSyncGraph, PUpdateFunExplicit};
+use crate::ibmfa_computations::ibmfa_pulse;
+use super::{prepare_fixes, greedy_fixes, greedy_reduce, PBNFix};
+use super::fixes::DriverSet;
+
+
+/*******************************************************************************
+ * Transient control
+ * =================
+ * A driver-set is applied only for a pulse of a few steps, then it is
+ * released and the network evolves freely. The control succeeds if
+ * the network stays in the target attractor after the release.
+ ******************************************************************************/
+
+/// Network configuration of the (fixed-point) attractor `vertices`.
+///
+/// If there are more vertices, the first one is taken.
+pub fn attractor_probs(sync_graph: &SymbSyncGraph, vertices: &GraphVertices)
+-> Vec<f32> {
+    let valuation = vertices.as_bdd().first_valuation().unwrap();
+    sync_graph.symbolic_context().state_variables().iter()
+        .map(|bdd_var| if valuation[*bdd_var] { 1.0 } else { 0.0 })
+        .collect()
+}
+
+/// Finds the shortest pulse of `pbn_fix` after which IBMFA of `sync_graph`
+/// converges to `target`.
+///
+/// Returns the pulse and the probabilities `iterations` steps after
+/// the release.
+///
+/// * `target` - The desired configuration, see `attractor_probs`.
+/// * `max_pulse` - The longest pulse tried.
+/// * `explicit_pupdate_funs_opt` - As in `ibmfa_entropy`.
+pub fn shortest_pulse(
+    sync_graph: &SymbSyncGraph,
+    pbn_fix: &PBNFix,
+    target: &[f32],
+    max_pulse: usize,
+    iterations: usize,
+    explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+) -> Option<(usize, Vec<f32>)> {
+    (0..=max_pulse)
+        .map(|pulse| {
+            let (_, probs, _) = ibmfa_pulse(
+                sync_graph, pbn_fix, pulse, iterations,
+                explicit_pupdate_funs_opt, None::<fn(&[f32])>, None, false);
+            (pulse, probs)
+        })
+        .find(|(_, probs)| distance(probs, target) == 0.0)
+}
+
+/// Finds a driver-set controlling `sync_graph` to the attractor `attr`
+/// by a pulse.
+///
+/// Vertex fixes are added greedily minimizing the distance from the attractor
+/// after the release of the longest pulse. Then unnecessary fixes are excluded
+/// (if `reduced`) and the shortest pulse is found.
+///
+/// Returns the fix, the pulse and the probabilities after the release, or
+/// `None` if even the longest pulse of all the fixes does not work.
+pub fn find_pulse_driver_set(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    max_pulse: usize,
+    reduced: bool,
+    attr: (&GraphVertices, &GraphColors),
+    verbose: bool,
+) -> Option<(PBNFix, usize, Vec<f32>)> {
+    let colors = attr.1.as_bdd().clone();
+    let explicit_pupdate_funs = sync_graph.explicit_pupdate_functions(&colors);
+    let target = attractor_probs(sync_graph, attr.0);
+
+    let pulse_distance = |pbn_fix: &PBNFix| {
+        let (_, probs, _) = ibmfa_pulse(
+            sync_graph, pbn_fix, max_pulse, iterations,
+            Some(&explicit_pupdate_funs), None::<fn(&[f32])>, None, false);
+        distance(&probs, &target)
+    };
+
+    let (available_fixes, mut pbn_fix) =
+        prepare_fixes(sync_graph, Some(attr.0), colors, true);
+    let min_distance = greedy_fixes(
+        sync_graph, available_fixes, &mut pbn_fix, pulse_distance, verbose);
+
+    if min_distance > 0.0 {
+        return None;
+    }
+
+    if reduced {
+        greedy_reduce(sync_graph, &mut pbn_fix, pulse_distance, verbose);
+    }
+
+    shortest_pulse(sync_graph, &pbn_fix, &target, max_pulse, iterations,
+                   Some(&explicit_pupdate_funs))
+        .map(|(pulse, probs)| (pbn_fix, pulse, probs))
+}
+
+/// Exact counterpart of `shortest_pulse` computed on the symbolic graph.
+///
+/// Returns the shortest pulse of `driver_set` after which all the states
+/// reach the attractor `attr` in all its colors, together with the colored
+/// vertices where the network is at the moment of the release.
+pub fn exact_shortest_pulse(
+    sync_graph: &SymbSyncGraph,
+    driver_set: &DriverSet,
+    attr: (&GraphVertices, &GraphColors),
+    max_pulse: usize,
+) -> Option<(usize, GraphColoredVertices)> {
+    let fixed_graph = sync_graph.fixed_by(driver_set);
+    let candidates = GraphColoredVertices::new(
+        attr.0.as_bdd().and(attr.1.as_bdd()), sync_graph.symbolic_context());
+    let attractor = sync_graph.fixed_point_attractors_in(&candidates)
+        .iter()
+        .fold(sync_graph.empty_colored_vertices(), |acc, attr| acc.union(attr));
+    let basin = sync_graph.predecessors(&attractor);
+
+    let mut reached = sync_graph.unit_colored_vertices().intersect_colors(attr.1);
+    for pulse in 0..=max_pulse {
+        if reached.minus(&basin).is_empty() {
+            return Some((pulse, reached));
+        }
+        reached = fixed_graph.post_synch(&reached);
+    }
+    None
+}
+
+/// Long-term behaviour after the release of a pulse.
+///
+/// Returns the attractors of `sync_graph` reachable from `released`, each
+/// with the share of the colored vertices of `released` in its basin.
+pub fn exact_release_distribution(
+    sync_graph: &SymbSyncGraph,
+    released: &GraphColoredVertices,
+) -> Vec<(GraphColoredVertices, f64)> {
+    let total = released.approx_cardinality();
+    sync_graph.attractors_in(released)
+        .into_iter()
+        .map(|attr| {
+            let share = released
+                .intersect(&sync_graph.predecessors(&attr))
+                .approx_cardinality() / total;
+            (attr, share)
+        })
+        .collect()
+}
+
+fn distance(probs: &[f32], target: &[f32]) -> f32 {
+    probs.iter()
+        .zip(target)
+        .map(|(p, t)| (p - t).abs())
+        .sum::<f32>() / probs.len() as f32
+}
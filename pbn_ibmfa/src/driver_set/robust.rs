@@ -0,0 +1,99 @@
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, GraphVertices};
+
+use crate::symbolic_sync_graph::SymbSyncGraph;
+use crate::utils::combinations;
+use super::controlled_colors;
+use super::fixes::DriverSet;
+
+
+/*******************************************************************************
+ * Robust driver-sets
+ * ==================
+ * A middle ground between the strong driver-set (working in all colors)
+ * and `colors_partition` (a driver-set for each color). A driver-set is
+ * scored by the share of colors where it controls the network.
+ ******************************************************************************/
+
+/// Share of `colors` in which `driver_set` controls `sync_graph` to `target`,
+/// see `controlled_colors`.
+///
+/// Returns the share and the uncontrolled colors.
+///
+/// * `weights` - Weighted classes of colors. Each class contributes by
+///     the share of its controlled colors times its weight. By default,
+///     all the colors weigh the same.
+pub fn controlled_share(
+    sync_graph: &SymbSyncGraph,
+    driver_set: &DriverSet,
+    target: &GraphVertices,
+    colors: &GraphColors,
+    weights: Option<&[(GraphColors, f64)]>,
+) -> (f64, GraphColors) {
+    let controlled = controlled_colors(sync_graph, driver_set, target, colors);
+    let share = match weights {
+        None => controlled.approx_cardinality() / colors.approx_cardinality(),
+        Some(weights) => {
+            let weights = weights.iter()
+                .filter(|(class, _)| !class.is_empty())
+                .collect::<Vec<_>>();
+            let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+            weights.iter()
+                .map(|(class, weight)| weight
+                    * class.intersect(&controlled).approx_cardinality()
+                    / class.approx_cardinality())
+                .sum::<f64>() / total
+        },
+    };
+    (share, colors.minus(&controlled))
+}
+
+/// Finds the smallest driver-set controlling `sync_graph` to `target`
+/// in at least `min_share` of `colors`.
+///
+/// All the driver-sets of vertex fixes consistent with `target` are tried
+/// by increasing size up to `max_size`. Among the smallest ones reaching
+/// `min_share`, the one with the largest share is returned.
+///
+/// Returns the driver-set, its share and the uncontrolled colors.
+///
+/// * `weights` - As in `controlled_share`.
+pub fn find_robust_driver_set(
+    sync_graph: &SymbSyncGraph,
+    target: &GraphVertices,
+    colors: &GraphColors,
+    min_share: f64,
+    max_size: usize,
+    weights: Option<&[(GraphColors, f64)]>,
+    verbose: bool,
+) -> Option<(DriverSet, f64, GraphColors)> {
+    let available_fixes = sync_graph.as_network().variables()
+        .flat_map(|var_id| [false, true].map(|value| (var_id, value)))
+        .filter(|&(var_id, value)|
+            !target.fix_network_variable(var_id, value).is_empty())
+        .collect::<Vec<_>>();
+
+    for size in 0..=max_size {
+        if verbose {
+            println!("======= {size} ========");
+        }
+
+        let best = combinations(&available_fixes, size)
+            .into_iter()
+            .map(|fixes| fixes.into_iter().collect::<DriverSet>())
+            // A variable cannot be fixed to both values
+            .filter(|driver_set| driver_set.len() == size)
+            .map(|driver_set| {
+                let (share, uncontrolled) = controlled_share(
+                    sync_graph, &driver_set, target, colors, weights);
+                (driver_set, share, uncontrolled)
+            })
+            .filter(|(_, share, _)| *share >= min_share)
+            .max_by(|(_, s1, _), (_, s2, _)| s1.total_cmp(s2));
+
+        if best.is_some() {
+            return best;
+        }
+    }
+    None
+}
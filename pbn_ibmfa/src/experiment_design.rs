@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::VariableId;
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, GraphVertices};
+
+use crate::driver_set::fixes::DriverSet;
+use crate::symbolic_sync_graph::SymbSyncGraph;
+use crate::parameter_names::{parameter_name, parse_parameter};
+
+
+/*******************************************************************************
+ * Experiment design
+ * =================
+ * The colors are the hypotheses about the network, equally likely a priori.
+ * A target (e.g. the attractor landscape or the correct driver-set)
+ * partitions the colors into classes, so does every measurement by its
+ * outcomes. Measurements are ranked by the expected information gain about
+ * the target, i.e. the decrease of its entropy after observing the outcome.
+ ******************************************************************************/
+
+/// A measurement narrowing down the colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Measurement {
+    /// Observing a single parameter, i.e. a row of a function table.
+    Parameter(BddVariable),
+    /// Fixing the variable to the value (a knockout or an overexpression)
+    /// and observing the fixed points the network settles in.
+    Perturbation(VariableId, bool),
+}
+
+impl Measurement {
+    /// The name of the measurement, i.e. the readable name of the parameter
+    /// (see `parameter_name`) or `{var_name}={value}`.
+    pub fn name(&self, sync_graph: &SymbSyncGraph) -> String {
+        match self {
+            Measurement::Parameter(bdd_var) =>
+                parameter_name(*bdd_var, sync_graph),
+            Measurement::Perturbation(var_id, value) => format!("{}={}",
+                sync_graph.as_network().get_variable_name(*var_id),
+                *value as u8),
+        }
+    }
+
+    /// Finds the measurement of the given `name`, parameters may be given
+    /// by their readable names.
+    pub fn from_name(name: &str, sync_graph: &SymbSyncGraph)
+    -> Result<Measurement, String> {
+        if let Some((var_name, value)) = name.rsplit_once('=') {
+            let var_id = sync_graph.as_network().as_graph()
+                .find_variable(var_name)
+                .ok_or(format!("The model does not contain variable \
+                    '{var_name}'."))?;
+            let value = match value {
+                "0" => false,
+                "1" => true,
+                _ => return Err(format!("Invalid perturbation '{name}'. \
+                    Expected value '0'/'1', found '{value}'.")),
+            };
+            Ok(Measurement::Perturbation(var_id, value))
+        } else {
+            parse_parameter(name, sync_graph).map(Measurement::Parameter)
+        }
+    }
+}
+
+/// All the parameters in `colors` and all the perturbations of variables.
+pub fn all_measurements(
+    sync_graph: &SymbSyncGraph,
+    colors: &GraphColors,
+    parameters: bool,
+    perturbations: bool,
+) -> Vec<Measurement> {
+    let mut measurements = Vec::new();
+    if parameters {
+        measurements.extend(colors.as_bdd().support_set().into_iter()
+            .filter(|bdd_var| sync_graph.symbolic_context()
+                .parameter_variables().contains(bdd_var))
+            .map(Measurement::Parameter));
+    }
+    if perturbations {
+        measurements.extend(sync_graph.as_network().variables()
+            .flat_map(|var_id| [false, true]
+                .map(|value| Measurement::Perturbation(var_id, value))));
+    }
+    measurements
+}
+
+/// Partitions `colors` by the set of fixed points of `sync_graph`.
+///
+/// Returns the classes with their fixed points (empty for colors without
+/// a fixed point).
+pub fn fixed_point_classes(sync_graph: &SymbSyncGraph, colors: &GraphColors)
+-> Vec<(GraphColors, GraphVertices)> {
+    let set = sync_graph.unit_colored_vertices().intersect_colors(colors);
+    let empty_vertices = sync_graph.empty_colored_vertices().vertices();
+    sync_graph.fixed_point_attractors_in(&set)
+        .iter()
+        .fold(vec![(colors.clone(), empty_vertices)], |classes, attr| {
+            let attr_colors = attr.colors();
+            classes.into_iter()
+                .flat_map(|(class, vertices)| [
+                    (class.intersect(&attr_colors),
+                     vertices.union(&attr.vertices())),
+                    (class.minus(&attr_colors), vertices),
+                ])
+                .filter(|(class, _)| !class.is_empty())
+                .collect()
+        })
+}
+
+/// Predicted outcomes of `measurement` in `colors`.
+///
+/// Returns the colors of each outcome with the fixed points observed
+/// after a perturbation (`None` for a parameter).
+pub fn outcomes(
+    sync_graph: &SymbSyncGraph,
+    measurement: &Measurement,
+    colors: &GraphColors,
+) -> Vec<(GraphColors, Option<GraphVertices>)> {
+    match measurement {
+        Measurement::Parameter(bdd_var) => [false, true].iter()
+            .map(|&value|
+                colors.copy(colors.as_bdd().var_select(*bdd_var, value)))
+            .filter(|outcome| !outcome.is_empty())
+            .map(|outcome| (outcome, None))
+            .collect(),
+        Measurement::Perturbation(var_id, value) => {
+            let fixed_graph = sync_graph.fixed_by(
+                &DriverSet::from([(*var_id, *value)]));
+            fixed_point_classes(&fixed_graph, colors).into_iter()
+                .map(|(outcome, vertices)| (outcome, Some(vertices)))
+                .collect()
+        },
+    }
+}
+
+/// Entropy of the classes of colors.
+pub fn entropy(classes: &[GraphColors]) -> f64 {
+    let sizes = classes.iter()
+        .map(|class| class.approx_cardinality())
+        .collect::<Vec<_>>();
+    let total = sizes.iter().sum::<f64>();
+    if total == 0.0 {
+        return 0.0;
+    }
+    sizes.iter()
+        .filter(|&&size| size > 0.0)
+        .map(|size| -size / total * (size / total).log2())
+        .sum()
+}
+
+/// Expected entropy of `target` after observing the class of `observed`.
+pub fn conditional_entropy(target: &[GraphColors], observed: &[GraphColors])
+-> f64 {
+    let total = observed.iter()
+        .map(|class| class.approx_cardinality())
+        .sum::<f64>();
+    observed.iter()
+        .map(|class| {
+            let subclasses = target.iter()
+                .map(|target_class| target_class.intersect(class))
+                .collect::<Vec<_>>();
+            class.approx_cardinality() / total * entropy(&subclasses)
+        })
+        .sum()
+}
+
+/// Classes of colors distinguished by both `classes1` and `classes2`.
+pub fn refine(classes1: &[GraphColors], classes2: &[GraphColors])
+-> Vec<GraphColors> {
+    classes1.iter()
+        .flat_map(|class1| classes2.iter()
+            .map(|class2| class1.intersect(class2)))
+        .filter(|class| !class.is_empty())
+        .collect()
+}
+
+/// A ranked measurement.
+#[derive(Clone, Debug)]
+pub struct RankedMeasurement {
+    pub measurement: Measurement,
+    pub outcomes: Vec<(GraphColors, Option<GraphVertices>)>,
+    /// Expected information gain about the target.
+    pub gain: f64,
+    pub cost: f64,
+}
+
+/// Ranks `measurements` by the expected information gain about `target`
+/// per unit of cost, descending.
+///
+/// * `target` - Non-empty classes of colors to distinguish, their union
+///     gives the colors of interest.
+/// * `costs` - Costs of the measurements, 1 by default.
+pub fn rank_measurements(
+    sync_graph: &SymbSyncGraph,
+    target: &[GraphColors],
+    measurements: &[Measurement],
+    costs: &HashMap<Measurement, f64>,
+) -> Vec<RankedMeasurement> {
+    let colors = union(target);
+    let target_entropy = entropy(target);
+
+    let mut ranked = measurements.iter()
+        .map(|measurement| {
+            let outcomes = outcomes(sync_graph, measurement, &colors);
+            let observed = outcomes.iter()
+                .map(|(outcome, _)| outcome.clone())
+                .collect::<Vec<_>>();
+            RankedMeasurement {
+                measurement: *measurement,
+                gain: target_entropy - conditional_entropy(target, &observed),
+                cost: costs.get(measurement).copied().unwrap_or(1.0),
+                outcomes,
+            }
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|m1, m2|
+        (m2.gain / m2.cost).total_cmp(&(m1.gain / m1.cost)));
+    ranked
+}
+
+/// A step of a sequential plan of measurements.
+#[derive(Clone, Debug)]
+pub struct PlanStep {
+    pub measurement: Measurement,
+    /// Expected information gain given the previous steps.
+    pub gain: f64,
+    pub cost: f64,
+    /// Expected entropy of the target after this step.
+    pub entropy: f64,
+}
+
+/// Plans the next `k` measurements.
+///
+/// The measurements are chosen greedily, each maximizing the expected
+/// information gain per unit of cost given the outcomes of the previous
+/// ones. The plan ends early if no measurement gains any information.
+///
+/// * `ranked` - Measurements with their outcomes, see `rank_measurements`.
+pub fn sequential_plan(
+    target: &[GraphColors],
+    ranked: &[RankedMeasurement],
+    k: usize,
+) -> Vec<PlanStep> {
+    let mut observed = vec![union(target)];
+    let mut current_entropy = entropy(target);
+    let mut plan: Vec<PlanStep> = Vec::new();
+
+    while plan.len() < k {
+        let best = ranked.iter()
+            .filter(|ranked| plan.iter()
+                .all(|step| step.measurement != ranked.measurement))
+            .map(|ranked| {
+                let outcomes = ranked.outcomes.iter()
+                    .map(|(outcome, _)| outcome.clone())
+                    .collect::<Vec<_>>();
+                let refined = refine(&observed, &outcomes);
+                let entropy = conditional_entropy(target, &refined);
+                (ranked, refined, entropy)
+            })
+            .max_by(|(r1, _, e1), (r2, _, e2)|
+                ((current_entropy - e1) / r1.cost)
+                    .total_cmp(&((current_entropy - e2) / r2.cost)));
+
+        match best {
+            Some((ranked, refined, entropy)) if entropy < current_entropy => {
+                plan.push(PlanStep {
+                    measurement: ranked.measurement,
+                    gain: current_entropy - entropy,
+                    cost: ranked.cost,
+                    entropy,
+                });
+                observed = refined;
+                current_entropy = entropy;
+            },
+            _ => break,
+        }
+    }
+    plan
+}
+
+/// Classes of a colors partition given by driver-sets, see `colors_partition`.
+pub fn driver_set_classes(
+    sync_graph: &SymbSyncGraph,
+    driver_sets: &[(Bdd, DriverSet)],
+) -> Vec<GraphColors> {
+    driver_sets.iter()
+        .map(|(colors, _)|
+            GraphColors::new(colors.clone(), sync_graph.symbolic_context()))
+        .collect()
+}
+
+/// Union of non-empty `classes`.
+fn union(classes: &[GraphColors]) -> GraphColors {
+    classes.iter()
+        .skip(1)
+        .fold(classes[0].clone(), |acc, class| acc.union(class))
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::Path;
+
+use json::JsonValue;
+use sha2::{Digest, Sha256};
+
+
+/*******************************************************************************
+ * Experiment specs
+ * ================
+ * An experiment is given by a spec file instead of the arguments of the
+ * command line, TOML or JSON with the same structure. The spec names
+ * the model, the command and its arguments (grouped in tables freely),
+ * it resolves to the arguments, so it gives the same results. The outputs
+ * embed the resolved spec, with all the defaults, and the hash of the model
+ * for provenance.
+ ******************************************************************************/
+
+/// Reads an experiment spec, TOML by the extension `.toml`, JSON otherwise.
+pub fn read_spec(path: &Path) -> Result<JsonValue, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!(
+        "Cannot read the spec '{}', err: {err}", path.display()))?;
+    let is_toml = path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    let spec = if is_toml {
+        content.parse::<toml::Table>()
+            .map(|table| toml_to_json(toml::Value::Table(table)))
+            .map_err(|err| err.to_string())
+    } else {
+        json::parse(&content).map_err(|err| err.to_string())
+    }.map_err(|err| format!("Invalid spec '{}', err: {err}", path.display()))?;
+    if !spec.is_object() {
+        return Err(format!("Invalid spec '{}', expected a table.",
+            path.display()));
+    }
+    Ok(spec)
+}
+
+/// Converts a TOML value to JSON, dates are written as strings.
+pub fn toml_to_json(value: toml::Value) -> JsonValue {
+    match value {
+        toml::Value::String(value) => value.into(),
+        toml::Value::Integer(value) => value.into(),
+        toml::Value::Float(value) => value.into(),
+        toml::Value::Boolean(value) => value.into(),
+        toml::Value::Datetime(value) => value.to_string().into(),
+        toml::Value::Array(values) => values.into_iter()
+            .map(toml_to_json)
+            .collect::<Vec<_>>()
+            .into(),
+        toml::Value::Table(table) => {
+            let mut object = JsonValue::new_object();
+            for (key, value) in table {
+                object[key] = toml_to_json(value);
+            }
+            object
+        },
+    }
+}
+
+/// The SHA-256 hash of the model file in hex, as by `sha256sum`.
+pub fn model_hash(path: &Path) -> Result<String, String> {
+    let content = fs::read(path).map_err(|err| format!(
+        "Cannot read the model '{}', err: {err}", path.display()))?;
+    Ok(Sha256::digest(content).iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
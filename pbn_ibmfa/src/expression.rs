@@ -0,0 +1,103 @@
+use biodivine_lib_bdd::boolean_expression::BooleanExpression;
+
+
+/*******************************************************************************
+ * Boolean expressions
+ * ===================
+ * The rules of update functions and the constraints on colors are Boolean
+ * expressions written differently (names, keywords), so each is tokenized
+ * on its own, but the tokens are parsed here. The binary operators from
+ * the weakest are `<=>`, `=>`, `|`, `^` and `&`, the negation binds
+ * the strongest. The implication is right associative, the others left.
+ ******************************************************************************/
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Name(String),
+    Const(bool),
+    Not,
+    And,
+    Or,
+    Xor,
+    Imp,
+    Iff,
+    Open,
+    Close,
+}
+
+/// Parses the expression given by `tokens`, the names are kept as
+/// variables.
+pub fn parse_tokens(tokens: &[Token]) -> Result<BooleanExpression, String> {
+    let mut parser = Parser { tokens, position: 0 };
+    let expression = parser.binary(0)?;
+    match tokens.get(parser.position) {
+        None => Ok(expression),
+        Some(token) => Err(format!("Unexpected {token:?}.")),
+    }
+}
+
+// A recursive descent parser, a level per binary operator
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    const LEVELS: [Token; 4] = [Token::Iff, Token::Imp, Token::Or, Token::Xor];
+
+    fn binary(&mut self, level: usize) -> Result<BooleanExpression, String> {
+        let Some(operator) = Self::LEVELS.get(level) else {
+            return self.conjunction();
+        };
+        let mut left = self.binary(level + 1)?;
+        while self.next_is(operator) {
+            if *operator == Token::Imp {
+                let right = Box::new(self.binary(level)?);
+                return Ok(BooleanExpression::Imp(Box::new(left), right));
+            }
+            let right = Box::new(self.binary(level + 1)?);
+            left = match operator {
+                Token::Iff => BooleanExpression::Iff(Box::new(left), right),
+                Token::Or => BooleanExpression::Or(Box::new(left), right),
+                _ => BooleanExpression::Xor(Box::new(left), right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<BooleanExpression, String> {
+        let mut expression = self.literal()?;
+        while self.next_is(&Token::And) {
+            expression = BooleanExpression::And(
+                Box::new(expression), Box::new(self.literal()?));
+        }
+        Ok(expression)
+    }
+
+    fn literal(&mut self) -> Result<BooleanExpression, String> {
+        let token = self.tokens.get(self.position)
+            .ok_or("Unexpected end of the expression.")?;
+        self.position += 1;
+        match token {
+            Token::Not => Ok(BooleanExpression::Not(Box::new(self.literal()?))),
+            Token::Open => {
+                let expression = self.binary(0)?;
+                if !self.next_is(&Token::Close) {
+                    return Err("Missing ')'.".into());
+                }
+                Ok(expression)
+            },
+            Token::Const(value) => Ok(BooleanExpression::Const(*value)),
+            Token::Name(name) => Ok(BooleanExpression::Variable(name.clone())),
+            token => Err(format!("Unexpected {token:?}.")),
+        }
+    }
+
+    fn next_is(&mut self, token: &Token) -> bool {
+        let is_next = self.tokens.get(self.position) == Some(token);
+        if is_next {
+            self.position += 1;
+        }
+        is_next
+    }
+}
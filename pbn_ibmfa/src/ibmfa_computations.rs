@@ -31,17 +31,8 @@ pub fn ibmfa_entropy(
     initial: Option<Vec<f32>>,
     verbose: bool,
 ) -> (f32, Vec<f32>, usize) {
-    let mut probs = match initial {
-        Some(initial) => initial,
-        None => sync_graph.as_network().variables()
-            .map(|var_id|
-                if let Some(fixed_prob) = pbn_fix.get_vertex(var_id) {
-                    if fixed_prob { 1.0 } else { 0.0 }
-                } else {
-                    0.5
-                })
-            .collect::<Vec<_>>(),
-    };
+    let mut probs = initial
+        .unwrap_or_else(|| initial_probs(sync_graph, pbn_fix));
 
     // In the case of finding color-fixes (not just vertex-fixes), now, it is
     // the time to compute explicit parametrizations of update functions,
@@ -74,6 +65,48 @@ pub fn ibmfa_entropy(
     (ent, probs, iterations)
 }
 
+/// Computes the IBMFA for `sync_graph` perturbed by `pbn_fix` only
+/// temporarily. The driver-set is applied for the first `pulse` steps, then
+/// it is released (the colors restriction of `pbn_fix` remains) and
+/// the simulation continues for another `iterations` steps.
+///
+/// Returns the same tuple as `ibmfa_entropy`, but for the released part.
+///
+/// * `pulse` - Number of steps the driver-set is applied.
+/// * `step_callback_opt` - Called after every iteration of both parts.
+/// * `initial` - Initial configuration. By default deduced from fixed
+///     variables in `pbn_fix` (only if `pulse > 0`), others are put to 0.5.
+/// * Other parameters as in `ibmfa_entropy`.
+pub fn ibmfa_pulse(
+    sync_graph: &SymbSyncGraph,
+    pbn_fix: &PBNFix,
+    pulse: usize,
+    iterations: usize,
+    explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    mut step_callback_opt: Option<impl FnMut(&[f32])>,
+    initial: Option<Vec<f32>>,
+    verbose: bool,
+) -> (f32, Vec<f32>, usize) {
+    let released = pbn_fix.released();
+    let initial = initial.unwrap_or_else(|| initial_probs(
+        sync_graph, if pulse > 0 { pbn_fix } else { &released }));
+
+    // Both parts share the colors, so the explicit form is computed once.
+    let explicit_pupdate_funs_binding = match explicit_pupdate_funs_opt {
+        Some(_) => None,
+        None => Some(sync_graph.explicit_pupdate_functions(&pbn_fix.colors())),
+    };
+    let explicit_pupdate_funs = explicit_pupdate_funs_opt.unwrap_or_else(
+        || explicit_pupdate_funs_binding.as_ref().unwrap());
+
+    let (_, probs, _) = ibmfa_entropy(
+        sync_graph, pbn_fix, pulse, false, Some(explicit_pupdate_funs),
+        step_callback_opt.as_mut(), Some(initial), verbose);
+    ibmfa_entropy(
+        sync_graph, &released, iterations, false, Some(explicit_pupdate_funs),
+        step_callback_opt, Some(probs), verbose)
+}
+
 /// Finds the minimizing fix for `sync_graph`.
 ///
 /// Returns the found fix, entropy, and the corresponding network configuration.
@@ -91,17 +124,33 @@ pub fn minimize_entropy<'a>(
     available_fixes: impl IntoIterator<Item = &'a UnitFix>,
     explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
     verbose: bool,
+) -> Option<(&'a UnitFix, f32, Vec<f32>)> {
+    minimize_entropy_counted(sync_graph, iterations, pbn_fix, available_fixes,
+        explicit_pupdate_funs_opt, &mut 0, verbose)
+}
+
+/// As `minimize_entropy`, adding the IBMFA simulations run to `runs`,
+/// one per fix.
+pub(crate) fn minimize_entropy_counted<'a>(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    pbn_fix: &mut PBNFix,
+    available_fixes: impl IntoIterator<Item = &'a UnitFix>,
+    explicit_pupdate_funs_opt: Option<&[PUpdateFunExplicit]>,
+    runs: &mut usize,
+    verbose: bool,
 ) -> Option<(&'a UnitFix, f32, Vec<f32>)> {
     available_fixes.into_iter()
         .map(|unit_fix| {
             if verbose {
                 println!("Try fix {}",
-                    unit_fix.to_str(&sync_graph.symbolic_context()));
+                    unit_fix.to_str(sync_graph));
             }
 
             pbn_fix.insert(unit_fix);
+            *runs += 1;
             let (ent, probs, index) = ibmfa_entropy(
-                &sync_graph, &pbn_fix, iterations, true,
+                sync_graph, pbn_fix, iterations, true,
                 explicit_pupdate_funs_opt, None::<fn(&[f32])>, None, false);
             pbn_fix.remove(unit_fix);
 
@@ -133,6 +182,17 @@ fn entropy(probs: &[f32]) -> f32 {
         .sum::<f32>() / probs.len() as f32
 }
 
+fn initial_probs(sync_graph: &SymbSyncGraph, pbn_fix: &PBNFix) -> Vec<f32> {
+    sync_graph.as_network().variables()
+        .map(|var_id|
+            if let Some(fixed_prob) = pbn_fix.get_vertex(var_id) {
+                if fixed_prob { 1.0 } else { 0.0 }
+            } else {
+                0.5
+            })
+        .collect()
+}
+
 fn clause_probability(
     clause: &BddPartialValuation,
     probs: &[f32],
@@ -3,3 +3,11 @@ pub mod utils;
 pub mod symbolic_sync_graph;
 pub mod ibmfa_computations;
 pub mod decision_tree;
+pub mod experiment_design;
+pub mod model_format;
+pub mod parameter_names;
+pub mod expression;
+pub mod simulation;
+pub mod output;
+pub mod experiment_spec;
+pub mod batch;
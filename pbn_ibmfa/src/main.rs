@@ -1,19 +1,46 @@
-use std::{process, fs, path::PathBuf};
+use std::{process, fs, path::{Path, PathBuf}, cmp::Reverse,
+    collections::HashMap};
 
-use biodivine_lib_bdd::{BddVariable, BddVariableSet};
+use biodivine_lib_bdd::{Bdd, BddValuation, BddVariable, BddVariableSet};
 use biodivine_lib_param_bn::BooleanNetwork;
 use biodivine_lib_param_bn::symbolic_async_graph::
-    {SymbolicContext, GraphColors};
+    {SymbolicContext, GraphColors, GraphVertices};
 use biodivine_lib_param_bn::biodivine_std::traits::Set;
 
-use json::{JsonValue, object, array};
-use clap::{Parser, Subcommand, Args};
+use json::{JsonValue, object};
+use clap::{Parser, Subcommand, Args, ValueEnum, ArgAction, CommandFactory,
+    FromArgMatches, ValueHint, error::ErrorKind, parser::ValueSource};
+use rand::{SeedableRng, rngs::StdRng};
 
 use pbn_ibmfa::symbolic_sync_graph::SymbSyncGraph;
-use pbn_ibmfa::utils::{add_self_regulations, variations_with_replacement};
-use pbn_ibmfa::ibmfa_computations::ibmfa_entropy;
+use pbn_ibmfa::simulation::{Convergence, ConvergenceLimits, InitialStates,
+    Trajectory, TrajectoryStats, color_trajectory, for_each_group};
+use pbn_ibmfa::experiment_spec::{model_hash, read_spec};
+use pbn_ibmfa::batch::{batch_models, peak_memory, run_batch};
+use pbn_ibmfa::output::{PlotLine, Table, line_plot_svg, xml_comment};
+use pbn_ibmfa::parameter_names::{parameter_name, parse_constraint,
+    parse_parameter_fixes};
+use pbn_ibmfa::utils::{add_self_regulations, bdd_to_str, dnf_to_str,
+    function_to_dnf};
+use pbn_ibmfa::ibmfa_computations::{ibmfa_entropy, ibmfa_pulse};
 use pbn_ibmfa::driver_set::{find_driver_set, colors_partition, PBNFix, UnitFix,
-    fixes::{DriverSet, UnitVertexFix}};
+    driver_set_to_json, driver_set_from_json,
+    fixes::{DriverSet, UnitVertexFix},
+    pulse::{find_pulse_driver_set, exact_shortest_pulse,
+        exact_release_distribution},
+    phenotype::{Phenotype, find_phenotype_driver_set, phenotype_vertices,
+        phenotype_distance, avoiding_driver_sets},
+    robust::{controlled_share, find_robust_driver_set}, controlled_colors};
+use pbn_ibmfa::decision_tree::{DecisionTree, SplitPredicate, TreeLimits,
+    LeafLabel, Behaviour, BehaviourKind, decision_tree,
+    TreeStats, decision_tree_from_partition, behaviour_partition, prune,
+    compare_trees,
+    serialization::{tree_to_json, tree_from_json}};
+use pbn_ibmfa::model_format::{ModelFormat, read_model_file, write_model,
+    comment_model};
+use pbn_ibmfa::experiment_design::{Measurement, all_measurements,
+    fixed_point_classes, driver_set_classes, entropy, rank_measurements,
+    sequential_plan};
 
 
 
@@ -24,6 +51,26 @@ enum Commands {
     Analysis(AnalysisArgs),
     /// Run the simulation for average PBN dynamics.
     Simulation(SimulationArgs),
+    /// Rank measurements by the expected information gain about
+    /// the attractor landscape or the driver-sets.
+    Design(DesignArgs),
+    /// Find the driver-set of a fully specified network (a color)
+    /// in a decision tree saved by `analysis --save-trees`.
+    Evaluate(EvaluateArgs),
+    /// Write the model in another format. Only fully specified models
+    /// can be written to formats other than .aeon.
+    Convert(ConvertArgs),
+    /// Write the fully specified network given by a color of the model.
+    /// By default the first color is taken.
+    Instantiate(InstantiateArgs),
+    /// List the admissible update functions of each variable with
+    /// the number of colors using them, and page through the colors.
+    Parametrizations(ParametrizationsArgs),
+    /// Run `analysis` of every model in a directory, or of the files
+    /// matching a pattern with `*` and `?` (quoted), and summarize them.
+    /// Every model is analysed in a separate process, its failure is
+    /// reported and the others continue.
+    Batch(BatchArgs),
 }
 
 #[derive(Args, Debug)]
@@ -40,32 +87,372 @@ struct AnalysisArgs {
     /// Do not reduce the driver-set.
     #[arg(long)]
     not_reduced: bool,
+    /// Find a driver-set for each attractor applied only temporarily,
+    /// by a pulse of at most MAX_PULSE steps.
+    #[arg(long, value_name = "MAX_PULSE")]
+    pulse: Option<usize>,
+    /// Find a driver-set for a phenotype (partial state) instead of
+    /// an attractor. Syntax: "{var_name}={value}". Value is "0" or "1".
+    #[arg(long)]
+    target: Vec<String>,
+    /// Find the smallest driver-sets after which no attractor satisfies
+    /// the phenotype. Syntax: "{var_name}={value}". Value is "0" or "1".
+    #[arg(long)]
+    avoid: Vec<String>,
+    /// Find the smallest driver-set for each attractor controlling
+    /// at least the given percentage of its colors.
+    #[arg(long, value_name = "PERCENT")]
+    robust: Option<f64>,
+    /// Report the share of colors controlled by the strong driver-sets.
+    #[arg(long, requires = "strong_dset")]
+    shares: bool,
+    /// The largest driver-set tried by the exhaustive searches
+    /// (avoiding a phenotype, robust driver-sets).
+    #[arg(long, default_value_t = 2)]
+    max_fixes: usize,
+    /// Find a decision tree over the colors of each attractor leading
+    /// to its driver-sets.
+    #[arg(long)]
+    decision_tree: bool,
+    /// The way the decision trees are built.
+    #[arg(long, value_enum, default_value_t = TreeMethod::Partition)]
+    tree_method: TreeMethod,
+    /// Build the decision trees by both methods and compare their size,
+    /// depth, the number of IBMFA runs and the controlled share of colors.
+    #[arg(long)]
+    compare_trees: bool,
+    /// The largest conjunction of parameters in one decision node.
+    #[arg(long, default_value_t = 2)]
+    max_conjunction: usize,
+    /// The maximal depth of the decision trees. Deeper nodes become leaves
+    /// with several driver-sets.
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// The minimal number of colors in a leaf of the decision trees.
+    #[arg(long, default_value_t = 0.0)]
+    min_leaf_size: f64,
+    /// The minimal information gain of a decision node.
+    #[arg(long, default_value_t = 0.0)]
+    min_gain: f64,
+    /// Prune the decision trees by cost-complexity with the given cost
+    /// of a leaf (a share of colors).
+    #[arg(long, value_name = "ALPHA")]
+    prune: Option<f64>,
+    /// Find a decision tree over all the colors leading to the behaviour
+    /// of the given kind.
+    #[arg(long, value_enum, value_name = "KIND")]
+    behaviour_tree: Option<BehaviourTree>,
+    /// The phenotype of the phenotype behaviour tree.
+    /// Syntax: "{var_name}={value}". Value is "0" or "1".
+    #[arg(long)]
+    behaviour_phenotype: Vec<String>,
+    /// Write the decision trees in the DOT (Graphviz) format to the directory,
+    /// one file per attractor and one for the behaviour tree.
+    #[arg(long, value_name = "DIR")]
+    dot: Option<PathBuf>,
+    /// Save the decision trees of driver-sets to the directory, one file
+    /// per attractor, see `evaluate`.
+    #[arg(long, value_name = "DIR")]
+    save_trees: Option<PathBuf>,
+    #[command(flatten)]
+    colors: ColorArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BehaviourTree {
+    /// The states of all the attractors.
+    Attractors,
+    /// The number of fixed points.
+    FixedPoints,
+    /// Whether some attractor satisfies the phenotype,
+    /// see `--behaviour-phenotype`.
+    Phenotype,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum TreeMethod {
+    /// Split the partition of colors given by driver-sets equality.
+    Partition,
+    /// Split the colors by (conjunctions of) parameter fixes found by IBMFA.
+    Recursive,
 }
 
 #[derive(Args, Debug)]
 struct SimulationArgs {
-    /// Compute the average dynamics by brute-force instead of IBMFA.
+    /// Compute the average dynamics by brute-force instead of IBMFA,
+    /// simulating every color from every initial state. The distribution
+    /// of probabilities across colors is output as well. Colors with
+    /// the same update functions of the simulated variables are simulated
+    /// once.
     #[arg(short, long)]
     brute_force: bool,
     /// Fix variable. Syntax: "{var_name}={value}". Value is "0" or "1".
     #[arg(short, long)]
     fix: Vec<String>,
+    /// Release the fixes after the given number of steps.
+    #[arg(long)]
+    pulse: Option<usize>,
+    /// Output the trajectory of every color with its index, see
+    /// `--brute-force`. Every color is simulated, even the ones with the same
+    /// update functions of the simulated variables.
+    #[arg(long, requires = "brute_force",
+        conflicts_with_all = ["samples", "per_group"])]
+    per_color: bool,
+    /// Output the trajectory of every group of colors with the same update
+    /// functions (or of every sample), see `--brute-force`.
+    #[arg(long, requires = "brute_force")]
+    per_group: bool,
+    /// Simulate only N colors picked uniformly at random (with replacement)
+    /// by brute-force, e.g. for large sets of colors.
+    #[arg(long, value_name = "N", requires = "brute_force")]
+    samples: Option<usize>,
+    /// The seed of the random samples.
+    #[arg(long, requires = "samples")]
+    seed: Option<u64>,
+    /// The number of bins of the histograms of probabilities across colors.
+    #[arg(long, default_value_t = 10, requires = "brute_force")]
+    bins: usize,
+    /// Initial probability of a variable, 0.5 by default.
+    /// Syntax: "{var_name}={probability}".
+    #[arg(long, value_name = "VAR=PROB")]
+    initial: Vec<String>,
+    /// Start in the fixed point with the given index in the output
+    /// of `analysis`, only the colors where it is an attractor are simulated.
+    #[arg(long, value_name = "INDEX",
+        conflicts_with_all = ["initial", "initial_states"])]
+    initial_attractor: Option<usize>,
+    /// Start in the states listed in the file, equally likely. A state
+    /// per line, the values of variables in the order of the network,
+    /// e.g. "0110". Empty lines and lines starting with '#' are skipped.
+    #[arg(long, value_name = "FILE", conflicts_with = "initial")]
+    initial_states: Option<PathBuf>,
+    /// Run until the probabilities converge to a fixed point or a cycle,
+    /// at most MAX_STEPS steps. The horizon starts at `--time-steps` and
+    /// is doubled until the convergence.
+    #[arg(long, value_name = "MAX_STEPS")]
+    until_convergence: Option<usize>,
+    /// The maximal difference of equal probabilities, see
+    /// `--until-convergence`.
+    #[arg(long, default_value_t = 1e-5, requires = "until_convergence")]
+    tolerance: f32,
+    /// The maximal length of detected cycles, see `--until-convergence`.
+    #[arg(long, default_value_t = 16, requires = "until_convergence")]
+    max_period: usize,
+    /// Plot the probabilities in time to an SVG file, with the bands
+    /// of the spread across colors of `--brute-force`.
+    #[arg(long, value_name = "FILE")]
+    svg: Option<PathBuf>,
+    #[command(flatten)]
+    colors: ColorArgs,
+}
+
+/// Restriction of the colors of the model.
+#[derive(Args, Debug)]
+struct ColorArgs {
+    /// Fix parameter. Syntax: "{name}={value}", e.g. "A(0B,1C)=1"
+    /// or "f_A[0,1]=1".
+    /// Value is "0" or "1".
+    #[arg(long)]
+    parameter: Vec<String>,
+    /// Restrict the colors by a Boolean expression over parameters,
+    /// e.g. "A(0B,1C) & !A(1B,1C)".
+    #[arg(long)]
+    constraint: Option<String>,
+    /// Take only the color with the given index among the colors satisfying
+    /// the parameters and the constraint, see `parametrizations`.
+    #[arg(long, value_name = "INDEX")]
+    color: Option<usize>,
+}
+
+
+#[derive(Args, Debug)]
+struct DesignArgs {
+    /// What the measurements should tell.
+    #[arg(long, value_enum, default_value_t = DesignTarget::Landscape)]
+    target: DesignTarget,
+    /// The attractor whose driver-sets are distinguished, given by its index
+    /// in the analysis output. Required by the driver-set target.
+    #[arg(long)]
+    attractor: Option<usize>,
+    /// Do not consider measuring parameters.
+    #[arg(long)]
+    no_parameters: bool,
+    /// Do not consider perturbations.
+    #[arg(long)]
+    no_perturbations: bool,
+    /// Cost of a measurement. Syntax: "{name}:{cost}". Name is a parameter
+    /// (e.g. "A(0B,1C)" or "f_A[0,1]") or a perturbation (e.g. "A=0").
+    /// The default cost is 1.
+    #[arg(long)]
+    cost: Vec<String>,
+    /// Plan the next K measurements.
+    #[arg(long, value_name = "K", default_value_t = 1)]
+    plan: usize,
+    /// The number of the best measurements reported.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Args, Debug)]
+struct EvaluateArgs {
+    /// The decision tree saved by `analysis --save-trees`.
+    #[arg(long)]
+    tree: PathBuf,
+    /// A fully specified instance of the model (in any supported format)
+    /// giving the color.
+    #[arg(long)]
+    network: Option<PathBuf>,
+    /// Fix parameter. Syntax: "{name}={value}", e.g. "A(0B,1C)=1"
+    /// or "f_A[0,1]=1".
+    /// Value is "0" or "1".
+    #[arg(long)]
+    parameter: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    /// The output file. Printed to the standard output if not given.
+    output: Option<PathBuf>,
+    /// The output format. By default given by the extension of the output.
+    #[arg(short, long)]
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+struct InstantiateArgs {
+    /// The output file. Printed to the standard output if not given.
+    output: Option<PathBuf>,
+    /// The output format. By default given by the extension of the output.
+    #[arg(short, long)]
+    format: Option<OutputFormat>,
+    /// Fix parameter. Syntax: "{name}={value}", e.g. "A(0B,1C)=1"
+    /// or "f_A[0,1]=1".
+    /// Value is "0" or "1". The other parameters are chosen by the index.
+    #[arg(long)]
+    parameter: Vec<String>,
+    /// The index of the color among the colors satisfying the parameters.
+    #[arg(short, long, default_value_t = 0)]
+    index: usize,
+    /// Pick a random color satisfying the parameters.
+    #[arg(short, long, conflicts_with = "index")]
+    random: bool,
+    /// The seed of the random pick.
+    #[arg(long, requires = "random")]
+    seed: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct ParametrizationsArgs {
+    /// Fix parameter. Syntax: "{name}={value}", e.g. "A(0B,1C)=1"
+    /// or "f_A[0,1]=1".
+    /// Value is "0" or "1".
+    #[arg(long)]
+    parameter: Vec<String>,
+    /// List the colors with their indices, as used by `instantiate`.
+    #[arg(short, long)]
+    list_colors: bool,
+    /// The index of the first listed color.
+    #[arg(long, default_value_t = 0, requires = "list_colors")]
+    offset: usize,
+    /// The maximal number of listed colors.
+    #[arg(long, default_value_t = 20, requires = "list_colors")]
+    limit: usize,
+}
+
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// Stop the analysis of a model after the given number of seconds.
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+    /// The arguments of `analysis`, e.g. `-- --strong-dset`. The sizes
+    /// of the strong driver-sets are summarized.
+    #[arg(last = true)]
+    analysis: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Aeon,
+    Sbml,
+    Bnet,
+    /// The rules of CANA, BooleanNet and PyBoolNet.
+    Cana,
+}
+
+impl From<OutputFormat> for ModelFormat {
+    fn from(format: OutputFormat) -> ModelFormat {
+        match format {
+            OutputFormat::Aeon => ModelFormat::Aeon,
+            OutputFormat::Sbml => ModelFormat::Sbml,
+            OutputFormat::Bnet => ModelFormat::Bnet,
+            OutputFormat::Cana => ModelFormat::Cana,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum DataFormat {
+    Json,
+    /// Comma-separated values.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum DesignTarget {
+    /// The set of fixed points.
+    Landscape,
+    /// The driver-set of an attractor, see `analysis --driver-sets`.
+    DriverSet,
 }
 
 
 /// A tool for running IBMFA on PBNs
 #[derive(Parser, Debug)]
+#[command(override_usage =
+    "pbn_ibmfa [OPTIONS] <PATH> <COMMAND>\n       pbn_ibmfa --spec <FILE>")]
 struct Cli {
     #[command(subcommand)]
-    command: Commands,
-    /// Path to the input .aeon file
-    path: std::path::PathBuf,
+    command: Option<Commands>,
+    /// Path to the input model, .aeon, SBML-qual (.sbml, .xml), .bnet
+    /// or the rules of CANA (.cana, .booleannet). The models of `batch`
+    /// are given by a directory or a pattern.
+    #[arg(required_unless_present = "spec")]
+    path: Option<PathBuf>,
     /// Pretty json output
     #[arg(short, long)]
     pretty_json: bool,
+    /// The output format. The tables of csv and tsv are the time series
+    /// of `simulation`, the attractors of `analysis`, the ranking
+    /// of `design` and the update functions of `parametrizations`.
+    #[arg(long, value_enum, default_value_t = DataFormat::Json)]
+    format: DataFormat,
+    /// Print the peak resident memory to the standard error at the end
+    /// (only on Linux).
+    #[arg(long)]
+    peak_memory: bool,
     /// Length of the simulation
     #[arg(short, long, default_value_t = 10)]
-    time_steps: u8,
+    time_steps: usize,
+    /// Run the experiment of a spec file (TOML if .toml, JSON otherwise)
+    /// instead of the arguments. The keys `model` and `command` give
+    /// the model and the command, `semantics` may be only "synchronous".
+    /// Other keys are the arguments (e.g. `time_steps = 20`), the keys
+    /// of tables are the arguments of the command (e.g. `[colors]`
+    /// with `constraint = "..."`), `arguments` the positional ones.
+    /// Paths are relative to the directory of the spec. Only the format
+    /// of the output (`--format`, `--pretty-json`) may be given with it,
+    /// overriding the spec.
+    #[arg(long, value_name = "FILE",
+        conflicts_with_all = ["path", "time_steps", "peak_memory"])]
+    spec: Option<PathBuf>,
+}
+
+impl Cli {
+    fn path(&self) -> &Path {
+        self.path.as_deref().unwrap()
+    }
 }
 
 
@@ -81,21 +468,11 @@ fn bdd_values_to_json(
     )
 }
 
-fn driver_set_to_json(driver_set: &DriverSet, context: &SymbolicContext)
--> JsonValue {
-    bdd_values_to_json(
-        driver_set.iter().map(|(var_id, value)|
-            (context.get_state_variable(*var_id), *value)),
-        context.bdd_variable_set()
-    )
-}
-
-fn load_model(path: &PathBuf) -> BooleanNetwork {
-    let model_string = fs::read_to_string(path).unwrap_or_else(|err| {
-        eprintln!("Cannot read the file, err: {}", err);
+fn load_model(path: &Path) -> BooleanNetwork {
+    let model = read_model_file(path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
         process::exit(1);
     });
-    let model = BooleanNetwork::try_from(model_string.as_str()).unwrap();
     add_self_regulations(model)
 }
 
@@ -111,24 +488,30 @@ fn add_state_variables(
     );
 }
 
+/// Parameter variables by their readable names, see `parameter_name`.
 fn add_parameter_variables(
-    context: &SymbolicContext,
+    sync_graph: &SymbSyncGraph,
     json_data: &mut json::JsonValue
 ) {
-    let bdd_var_set = context.bdd_variable_set();
     json_data["parameter_variables"] = JsonValue::Array(
-        context.parameter_variables().iter()
-            .map(|bdd_var| JsonValue::String(bdd_var_set.name_of(*bdd_var)))
+        sync_graph.symbolic_context().parameter_variables().iter()
+            .map(|bdd_var|
+                JsonValue::String(parameter_name(*bdd_var, sync_graph)))
             .collect::<json::Array>()
     );
 }
 
-fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
+fn main_analysis(
+    args: &Cli,
+    provenance: &JsonValue,
+    analysis_args: &AnalysisArgs,
+) {
     // Load the model from a file
-    let model = load_model(&args.path);
+    let model = load_model(args.path());
 
     // Compute the symbolic synchronous transition graph
-    let sync_graph = SymbSyncGraph::new(model);
+    let (sync_graph, restriction) =
+        restrict_colors(SymbSyncGraph::new(model), &analysis_args.colors);
     let context = sync_graph.symbolic_context();
     let bdd_var_set = context.bdd_variable_set();
 
@@ -137,13 +520,16 @@ fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
     let mut json_data = json::JsonValue::new_object();
 
     add_state_variables(context, &mut json_data);
-    add_parameter_variables(context, &mut json_data);
+    add_parameter_variables(&sync_graph, &mut json_data);
 
     json_data["colors"] = sync_graph.unit_colors().approx_cardinality().into();
+    if !restriction.is_null() {
+        json_data["restriction"] = restriction;
+    }
 
     if analysis_args.strong_dset_free {
         let (pbn_fix, probs) = find_driver_set(
-            &sync_graph, args.time_steps as usize,
+            &sync_graph, args.time_steps,
             !analysis_args.not_reduced, None, true, false);
 
         let state = bdd_values_to_json(
@@ -159,12 +545,86 @@ fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
         };
     }
 
+    if !analysis_args.target.is_empty() {
+        let phenotype =
+            parse_phenotype(&analysis_args.target, sync_graph.as_network());
+        json_data["phenotype"] = phenotype_driver_set_to_json(
+            &sync_graph, args.time_steps,
+            !analysis_args.not_reduced, &phenotype);
+    }
+
+    if !analysis_args.avoid.is_empty() {
+        let phenotype =
+            parse_phenotype(&analysis_args.avoid, sync_graph.as_network());
+        let driver_sets = avoiding_driver_sets(
+            &sync_graph, &phenotype, &sync_graph.unit_colors(),
+            analysis_args.max_fixes, false);
+
+        json_data["avoid"] = object!{
+            phenotype: driver_set_to_json(&phenotype, context),
+            driver_sets: driver_sets.iter()
+                .map(|(driver_set, failing)| object!{
+                    driver_set: driver_set_to_json(driver_set, context),
+                    failing_colors: failing.approx_cardinality(),
+                    failing_colors_bdd: bdd_to_str(failing.as_bdd(), context),
+                })
+                .collect::<json::Array>(),
+        };
+    }
+
+    for dir in [&analysis_args.dot, &analysis_args.save_trees]
+            .into_iter().flatten() {
+        fs::create_dir_all(dir).unwrap_or_else(|err| {
+            eprintln!("Cannot create the directory, err: {}", err);
+            process::exit(1);
+        });
+    }
+
+    let limits = TreeLimits {
+        max_depth: analysis_args.max_depth,
+        min_leaf_size: analysis_args.min_leaf_size,
+        min_gain: analysis_args.min_gain,
+    };
+
+    // Decision tree of behaviours
+    if let Some(behaviour_tree) = analysis_args.behaviour_tree {
+        let kind = match behaviour_tree {
+            BehaviourTree::Attractors => BehaviourKind::Attractors,
+            BehaviourTree::FixedPoints => BehaviourKind::FixedPoints,
+            BehaviourTree::Phenotype => {
+                if analysis_args.behaviour_phenotype.is_empty() {
+                    eprintln!("Err: Expected '--behaviour-phenotype'.");
+                    process::exit(1);
+                }
+                BehaviourKind::Phenotype(parse_phenotype(
+                    &analysis_args.behaviour_phenotype, sync_graph.as_network()))
+            },
+        };
+        let colors = sync_graph.unit_colors();
+        let partition = behaviour_partition(&sync_graph, &kind, &colors);
+        let mut tree = decision_tree_from_partition(
+            &sync_graph, colors.as_bdd(), &partition,
+            analysis_args.max_conjunction, &limits);
+        if let Some(alpha) = analysis_args.prune {
+            tree = prune(&tree, colors.as_bdd(), alpha);
+        }
+
+        json_data["behaviour-tree"] = decision_tree_to_json(
+            &tree, &colors, &sync_graph, "behaviour",
+            &|behaviour| behaviour_to_json(behaviour, &sync_graph));
+        if let Some(dir) = &analysis_args.dot {
+            write_dot(&dir.join("behaviour.dot"), &tree, &colors, &sync_graph,
+                provenance);
+        }
+    }
+
     // Compute the attractors
     let mut attrs = sync_graph.fixed_point_attractors();
-    attrs.sort_by(|a1, a2| a2.exact_cardinality().cmp(&a1.exact_cardinality()));
+    attrs.sort_by_key(|attr| Reverse(attr.exact_cardinality()));
 
     json_data["attractors"] = JsonValue::Array(attrs.iter()
-        .map(|attr| {
+        .enumerate()
+        .map(|(index, attr)| {
             let state = bdd_values_to_json(
                 attr.vertices().as_bdd().first_clause().unwrap().to_values(),
                 bdd_var_set
@@ -175,7 +635,11 @@ fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
                 state: state,
             };
 
-            if !analysis_args.strong_dset && !analysis_args.driver_sets {
+            if !analysis_args.strong_dset && !analysis_args.driver_sets
+                    && !analysis_args.decision_tree
+                    && !analysis_args.compare_trees
+                    && analysis_args.pulse.is_none()
+                    && analysis_args.robust.is_none() {
                 return attr_json;
             }
 
@@ -184,24 +648,56 @@ fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
             // Strong driver-set
             if analysis_args.strong_dset {
                 let (pbn_fix, _) = find_driver_set(
-                    &sync_graph, args.time_steps as usize,
-                    !analysis_args.not_reduced, Some(attr_tuple), true, false);
+                    &sync_graph, args.time_steps, !analysis_args.not_reduced,
+                    Some(attr_tuple), true, false);
 
                 attr_json["strong-driver-set"] = driver_set_to_json(
                     pbn_fix.get_driver_set(), context);
+
+                if analysis_args.shares {
+                    let (share, uncontrolled) = controlled_share(
+                        &sync_graph, pbn_fix.get_driver_set(),
+                        attr_tuple.0, attr_tuple.1, None);
+                    attr_json["strong-driver-set-share"] =
+                        share_to_json(share, &uncontrolled, context);
+                }
+            }
+
+            // Smallest driver-set working in the required share of colors
+            if let Some(percent) = analysis_args.robust {
+                attr_json["robust-driver-set"] = match find_robust_driver_set(
+                        &sync_graph, attr_tuple.0, attr_tuple.1,
+                        percent / 100.0, analysis_args.max_fixes, None, false) {
+                    Some((driver_set, share, uncontrolled)) => {
+                        let mut robust_json =
+                            share_to_json(share, &uncontrolled, context);
+                        robust_json["driver_set"] =
+                            driver_set_to_json(&driver_set, context);
+                        robust_json
+                    },
+                    None => JsonValue::Null,
+                };
             }
 
             // Parametrizations partition by driver-set equality
-            if analysis_args.driver_sets {
-                let mut driver_sets = colors_partition(
-                    &sync_graph, args.time_steps as usize,
-                    !analysis_args.not_reduced, attr_tuple, false);
-                driver_sets.sort_by(|(c1, _), (c2, _)|
-                    c2.exact_cardinality().cmp(&c1.exact_cardinality()));
-                let driver_sets = driver_sets.into_iter()
+            let partition = (analysis_args.driver_sets
+                    || (analysis_args.decision_tree
+                        && analysis_args.tree_method == TreeMethod::Partition))
+                .then(|| {
+                    let mut driver_sets = colors_partition(
+                        &sync_graph, args.time_steps,
+                        !analysis_args.not_reduced, attr_tuple, false);
+                    driver_sets.sort_by_key(|(colors, _)|
+                        Reverse(colors.exact_cardinality()));
+                    driver_sets
+                });
+
+            if let (true, Some(driver_sets)) =
+                    (analysis_args.driver_sets, &partition) {
+                let driver_sets = driver_sets.iter()
                     .map(|(colors, driver_set)| object!{
-                        driver_set: driver_set_to_json(&driver_set, context),
-                        colors: GraphColors::new(colors, context)
+                        driver_set: driver_set_to_json(driver_set, context),
+                        colors: GraphColors::new(colors.clone(), context)
                             .approx_cardinality()
                     })
                     .collect::<json::Array>();
@@ -209,14 +705,261 @@ fn main_analysis(args: &Cli, analysis_args: &AnalysisArgs) {
                 attr_json["driver-sets"] = JsonValue::Array(driver_sets);
             }
 
+            // Decision tree leading to the driver-sets
+            if analysis_args.decision_tree {
+                let mut tree = match &partition {
+                    Some(driver_sets) => decision_tree_from_partition(
+                        &sync_graph, attr_tuple.1.as_bdd(), driver_sets,
+                        analysis_args.max_conjunction, &limits),
+                    None => decision_tree(
+                        &sync_graph, args.time_steps, attr_tuple,
+                        !analysis_args.not_reduced,
+                        analysis_args.max_conjunction),
+                };
+                if let Some(alpha) = analysis_args.prune {
+                    tree = prune(&tree, attr_tuple.1.as_bdd(), alpha);
+                }
+
+                attr_json["decision-tree"] = decision_tree_to_json(
+                    &tree, attr_tuple.1, &sync_graph, "driver_set",
+                    &|driver_set| driver_set_to_json(driver_set, context));
+
+                if let Some(dir) = &analysis_args.dot {
+                    let path = dir.join(format!("attractor_{index}.dot"));
+                    write_dot(&path, &tree, attr_tuple.1, &sync_graph,
+                        provenance);
+                }
+                if let Some(dir) = &analysis_args.save_trees {
+                    let mut tree_json = tree_to_json(
+                        &tree, attr_tuple.1, &sync_graph,
+                        &|driver_set| driver_set_to_json(driver_set, context));
+                    tree_json["attractor"] =
+                        vertices_to_json(attr_tuple.0, &sync_graph);
+                    tree_json["spec"] = provenance["spec"].clone();
+                    tree_json["model-hash"] =
+                        provenance["model_hash"].clone();
+                    let path = dir.join(format!("attractor_{index}.json"));
+                    fs::write(path, json::stringify_pretty(tree_json, 4))
+                        .unwrap_or_else(|err| {
+                            eprintln!("Cannot write the file, err: {}", err);
+                            process::exit(1);
+                        });
+                }
+            }
+
+            // Both methods of building decision trees
+            if analysis_args.compare_trees {
+                let [partition, recursive] = compare_trees(
+                    &sync_graph, args.time_steps, attr_tuple,
+                    !analysis_args.not_reduced, analysis_args.max_conjunction);
+                attr_json["tree-comparison"] = object!{
+                    partition: tree_stats_to_json(&partition.1),
+                    recursive: tree_stats_to_json(&recursive.1),
+                };
+            }
+
+            // Driver-set applied temporarily
+            if let Some(max_pulse) = analysis_args.pulse {
+                attr_json["pulse-driver-set"] = pulse_driver_set_to_json(
+                    &sync_graph, args.time_steps, max_pulse,
+                    !analysis_args.not_reduced, attr_tuple);
+            }
+
             attr_json
         })
         .collect::<json::Array>()
     );
 
-    print_json(json_data, args.pretty_json);
+    print_output(json_data, args, provenance, Some(attractors_table));
+}
+
+
+/// * `label_key` - The key of the leaf labels, e.g. "driver_set".
+///     Impure leaves list them under the plural.
+fn decision_tree_to_json<L: LeafLabel>(
+    tree: &DecisionTree<L>,
+    colors: &GraphColors,
+    sync_graph: &SymbSyncGraph,
+    label_key: &str,
+    label_to_json: &dyn Fn(&L) -> JsonValue,
+) -> JsonValue {
+    let context = sync_graph.symbolic_context();
+    match tree {
+        DecisionTree::Leaf(label) => {
+            let mut leaf_json = object!{ colors: colors.approx_cardinality() };
+            leaf_json[label_key] = label_to_json(label);
+            leaf_json
+        },
+        DecisionTree::ImpureLeaf(labels) => {
+            let total = colors.approx_cardinality();
+            let mut leaf_json = object!{ colors: total };
+            leaf_json[format!("{label_key}s")] = labels.iter()
+                .map(|(class_colors, label)| {
+                    let class_colors = GraphColors::new(
+                        class_colors.clone(), context).approx_cardinality();
+                    let mut class_json = object!{
+                        colors: class_colors,
+                        share: class_colors / total,
+                    };
+                    class_json[label_key] = label_to_json(label);
+                    class_json
+                })
+                .collect::<json::Array>()
+                .into();
+            leaf_json
+        },
+        DecisionTree::Node(node) => {
+            let split = node.get_predicates().iter()
+                .map(|(predicate, value)| object!{
+                    type: match predicate {
+                        SplitPredicate::Parameters(_) => "parameters",
+                        SplitPredicate::Function(..) => "function",
+                        SplitPredicate::Essential(..) => "essential",
+                    },
+                    predicate: predicate.to_str(sync_graph),
+                    value: *value,
+                })
+                .collect::<json::Array>();
+            let fix = node.get_fix();
+            let [low, high] = node.get_childs();
+            object!{
+                colors: colors.approx_cardinality(),
+                split: split,
+                // Colors not satisfying the split
+                low: decision_tree_to_json(
+                    low, &colors.copy(colors.as_bdd().and_not(fix)),
+                    sync_graph, label_key, label_to_json),
+                high: decision_tree_to_json(
+                    high, &colors.copy(colors.as_bdd().and(fix)),
+                    sync_graph, label_key, label_to_json),
+            }
+        },
+    }
+}
+
+fn tree_stats_to_json(stats: &TreeStats) -> JsonValue {
+    object!{
+        nodes: stats.nodes,
+        leaves: stats.leaves,
+        depth: stats.depth,
+        ibmfa_runs: stats.ibmfa_runs,
+        seconds: stats.seconds,
+        controlled_share: stats.controlled_share,
+    }
 }
 
+fn behaviour_to_json(behaviour: &Behaviour, sync_graph: &SymbSyncGraph)
+-> JsonValue {
+    match behaviour {
+        Behaviour::Attractors(vertices) => object!{
+            attractor_states: vertices_to_json(vertices, sync_graph),
+        },
+        Behaviour::FixedPoints(num) => object!{ fixed_points: *num },
+        Behaviour::PhenotypeReachable(reachable) =>
+            object!{ phenotype_reachable: *reachable },
+    }
+}
+
+fn write_dot<L: LeafLabel>(
+    path: &PathBuf,
+    tree: &DecisionTree<L>,
+    colors: &GraphColors,
+    sync_graph: &SymbSyncGraph,
+    provenance: &JsonValue,
+) {
+    let header = provenance_lines(provenance).iter()
+        .map(|line| format!("// {line}\n"))
+        .collect::<String>();
+    let dot = header + &tree.to_dot(colors, sync_graph);
+    fs::write(path, dot).unwrap_or_else(|err| {
+        eprintln!("Cannot write the file, err: {}", err);
+        process::exit(1);
+    });
+}
+
+fn share_to_json(share: f64, uncontrolled: &GraphColors, context: &SymbolicContext)
+-> JsonValue {
+    object!{
+        share: share,
+        uncontrolled_colors: uncontrolled.approx_cardinality(),
+        uncontrolled_colors_bdd: bdd_to_str(uncontrolled.as_bdd(), context),
+    }
+}
+
+fn phenotype_driver_set_to_json(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    reduced: bool,
+    phenotype: &Phenotype,
+) -> JsonValue {
+    let context = sync_graph.symbolic_context();
+    let colors = sync_graph.unit_colors();
+    let (pbn_fix, probs) = find_phenotype_driver_set(
+        sync_graph, iterations, reduced, phenotype, &colors, false);
+
+    // Verify the driver-set exactly
+    let controlled = controlled_colors(
+        sync_graph, pbn_fix.get_driver_set(),
+        &phenotype_vertices(sync_graph, phenotype), &colors);
+
+    object!{
+        target: driver_set_to_json(phenotype, context),
+        driver_set: driver_set_to_json(pbn_fix.get_driver_set(), context),
+        distance: phenotype_distance(&probs, phenotype),
+        controlled_colors: controlled.approx_cardinality(),
+    }
+}
+
+fn pulse_driver_set_to_json(
+    sync_graph: &SymbSyncGraph,
+    iterations: usize,
+    max_pulse: usize,
+    reduced: bool,
+    attr: (&GraphVertices, &GraphColors),
+) -> JsonValue {
+    let context = sync_graph.symbolic_context();
+    let Some((pbn_fix, pulse, probs)) = find_pulse_driver_set(
+            sync_graph, iterations, max_pulse, reduced, attr, false) else {
+        return JsonValue::Null;
+    };
+    let driver_set = pbn_fix.get_driver_set();
+
+    let mut pulse_json = object!{
+        driver_set: driver_set_to_json(driver_set, context),
+        pulse: pulse,
+        released: probs.as_slice(),
+        exact_pulse: JsonValue::Null,
+    };
+
+    // Verify the pulse exactly, report where the network goes after release
+    if let Some((exact_pulse, released)) =
+            exact_shortest_pulse(sync_graph, driver_set, attr, max_pulse) {
+        pulse_json["exact_pulse"] = exact_pulse.into();
+        pulse_json["release_distribution"] = JsonValue::Array(
+            exact_release_distribution(sync_graph, &released).iter()
+                .map(|(attr, share)| object!{
+                    states: vertices_to_json(&attr.vertices(), sync_graph),
+                    share: *share,
+                })
+                .collect::<json::Array>());
+    }
+
+    pulse_json
+}
+
+fn vertices_to_json(vertices: &GraphVertices, sync_graph: &SymbSyncGraph)
+-> JsonValue {
+    let context = sync_graph.symbolic_context();
+    JsonValue::Array(sync_graph.get_all_false()
+        .project(context.state_variables())
+        .and(vertices.as_bdd())
+        .sat_valuations()
+        .map(|valuation| bdd_values_to_json(
+            context.state_variables().iter()
+                .map(|bdd_var| (*bdd_var, valuation[*bdd_var])),
+            context.bdd_variable_set()))
+        .collect::<json::Array>())
+}
 
 fn parse_fixes(fixes: &[String], model: &BooleanNetwork)
 -> Result<Vec<UnitVertexFix>, String> {
@@ -244,119 +987,473 @@ fn parse_fixes(fixes: &[String], model: &BooleanNetwork)
         .collect()
 }
 
-fn main_simulation(args: &Cli, sim_args: &SimulationArgs) {
-    let model = load_model(&args.path);
+/// Parses initial probabilities of variables. Syntax: "{name}={probability}",
+/// the other variables are 0.5.
+fn parse_initial_probabilities(probs: &[String], model: &BooleanNetwork)
+-> Result<InitialStates, String> {
+    let mut initial = vec![0.5; model.num_vars()];
+    for prob in probs {
+        let (name, value) = prob.split_once('=')
+            .ok_or(format!("Invalid initial probability '{prob}'. \
+                Expected format '{{name}}={{probability}}'."))?;
+        let var_id = model.as_graph().find_variable(name.trim())
+            .ok_or(format!("Invalid initial probability '{prob}'. \
+                The model does not contain variable '{name}'."))?;
+        initial[var_id.to_index()] = value.trim().parse::<f32>().ok()
+            .filter(|value| (0.0..=1.0).contains(value))
+            .ok_or(format!("Invalid initial probability '{prob}'. \
+                Expected a number in [0, 1], found '{value}'."))?;
+    }
+    Ok(InitialStates::Probabilities(initial))
+}
+
+/// Reads initial states, a state per line as the values of `num_vars`
+/// variables, e.g. "0110". Empty lines and lines starting with '#'
+/// are skipped.
+fn read_initial_states(path: &Path, num_vars: usize)
+-> Result<InitialStates, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!(
+        "Cannot read initial states '{}', err: {err}", path.display()))?;
+    let states = content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let state = line.chars()
+                .map(|value| match value {
+                    '0' => Ok(false),
+                    '1' => Ok(true),
+                    _ => Err(format!("Invalid initial state '{line}'. \
+                        Expected values '0'/'1', found '{value}'.")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if state.len() != num_vars {
+                return Err(format!("Invalid initial state '{line}'. \
+                    Expected {num_vars} values."));
+            }
+            Ok(state)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if states.is_empty() {
+        return Err(format!("No initial states in '{}'.", path.display()));
+    }
+    Ok(InitialStates::States(states))
+}
+
+fn parse_phenotype(values: &[String], model: &BooleanNetwork) -> Phenotype {
+    parse_fixes(values, model)
+        .unwrap_or_else(|err| {
+            eprintln!("Err: {err}");
+            process::exit(1);
+        })
+        .into_iter()
+        .map(|UnitVertexFix { var_id, value }| (var_id, value))
+        .collect()
+}
+
+/// Restricts the colors of `sync_graph` by the parameters, the constraint
+/// and the color index of `color_args`, in this order.
+///
+/// Returns the restricted graph and the restriction in JSON with the number
+/// of remaining colors, null if no restriction is given.
+fn restrict_colors(sync_graph: SymbSyncGraph, color_args: &ColorArgs)
+-> (SymbSyncGraph, JsonValue) {
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+    let context = sync_graph.symbolic_context();
+    let parameters =
+        parse_parameter_fixes(&color_args.parameter, &sync_graph)
+        .unwrap_or_else(|err| exit_on_err(err));
+    let mut colors = sync_graph.unit_colors();
+    colors = colors.copy(colors.as_bdd().select(&parameters));
+    if let Some(constraint) = &color_args.constraint {
+        let constraint = parse_constraint(constraint, &sync_graph)
+            .unwrap_or_else(|err| exit_on_err(err));
+        colors = colors.copy(colors.as_bdd().and(&constraint));
+    }
+    if let Some(index) = color_args.color {
+        let color = sync_graph.nth_color(&colors, index)
+            .unwrap_or_else(|| exit_on_err(format!("There is no such color, \
+                {} colors satisfy the parameters and the constraint.",
+                colors.approx_cardinality())));
+        let color = context.parameter_variables().iter()
+            .map(|bdd_var| (*bdd_var, color[*bdd_var]))
+            .collect::<Vec<_>>();
+        colors = colors.copy(colors.as_bdd().select(&color));
+    }
+    if colors.is_empty() {
+        exit_on_err(
+            "No color satisfies the network and the restriction.".into());
+    }
+
+    let mut restriction = JsonValue::Null;
+    if !parameters.is_empty() || color_args.constraint.is_some()
+            || color_args.color.is_some() {
+        restriction = object!{
+            parameters: parameters.iter()
+                .map(|(bdd_var, value)| object!{
+                    variable: parameter_name(*bdd_var, &sync_graph),
+                    value: *value,
+                })
+                .collect::<json::Array>(),
+            constraint: color_args.constraint.clone(),
+            color: color_args.color,
+            colors: colors.approx_cardinality(),
+        };
+    }
+    (sync_graph.restricted(&colors), restriction)
+}
+
+fn main_simulation(
+    args: &Cli,
+    provenance: &JsonValue,
+    sim_args: &SimulationArgs,
+) {
+    let model = load_model(args.path());
     let user_fixes = parse_fixes(&sim_args.fix, &model).unwrap_or_else(|err| {
         eprintln!("Err: {err}");
         process::exit(1);
     });
-    let sync_graph = SymbSyncGraph::new(model);
+    let (mut sync_graph, restriction) =
+        restrict_colors(SymbSyncGraph::new(model), &sim_args.colors);
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+
+    let num_vars = sync_graph.as_network().num_vars();
+    let mut initial_attractor = JsonValue::Null;
+    let initial_states = if let Some(index) = sim_args.initial_attractor {
+        // The same order as in the output of `analysis`
+        let mut attrs = sync_graph.fixed_point_attractors();
+        attrs.sort_by_key(|attr| Reverse(attr.exact_cardinality()));
+        let attr = attrs.get(index).unwrap_or_else(|| exit_on_err(format!(
+            "Invalid attractor {index}, the model has {} attractors.",
+            attrs.len())));
+        let state = attr.vertices().as_bdd().first_clause().unwrap();
+        let context = sync_graph.symbolic_context();
+        initial_attractor = object!{
+            index: index,
+            state: bdd_values_to_json(state.to_values(),
+                context.bdd_variable_set()),
+        };
+        let state = context.state_variables().iter()
+            .map(|bdd_var| state.get_value(*bdd_var) == Some(true))
+            .collect();
+        sync_graph = sync_graph.restricted(&attr.colors());
+        InitialStates::States(vec![state])
+    } else if let Some(path) = &sim_args.initial_states {
+        read_initial_states(path, num_vars)
+            .unwrap_or_else(|err| exit_on_err(err))
+    } else {
+        parse_initial_probabilities(&sim_args.initial, sync_graph.as_network())
+            .unwrap_or_else(|err| exit_on_err(err))
+    };
+
     let context = sync_graph.symbolic_context();
-    let bdd_var_set = context.bdd_variable_set();
     let mut pbn_fix = PBNFix::new(sync_graph.unit_colors().into_bdd());
     for unit_vertex_fix in user_fixes {
         pbn_fix.insert(&UnitFix::Vertex(unit_vertex_fix));
     }
     let fixes = pbn_fix.get_driver_set();
+    let time_steps = args.time_steps;
 
     let mut json_data = json::JsonValue::new_object();
 
     add_state_variables(context, &mut json_data);
-
-    json_data["simulation"] = json::JsonValue::new_object();
-    for var_id in sync_graph.as_network().variables() {
-        let name = sync_graph.as_network().get_variable_name(var_id);
-        json_data["simulation"][name] =
-            if let Some(value) = fixes.get(&var_id) {
-                array![if *value { 1.0 } else { 0.0 }]
-            } else {
-                array![0.5]
-            };
+    json_data["colors"] = sync_graph.unit_colors().approx_cardinality().into();
+    if !restriction.is_null() {
+        json_data["restriction"] = restriction;
+    }
+    if !initial_attractor.is_null() {
+        json_data["initial-attractor"] = initial_attractor;
+    }
+    if let InitialStates::States(states) = &initial_states {
+        json_data["initial-states"] = states.len().into();
     }
 
-    let mut all_probs: Vec<Vec<f32>>;
-
-    if sim_args.brute_force {
-        let vars_num = context.num_state_variables();
+    let initial = if sim_args.pulse != Some(0) {
+        initial_states.probabilities(fixes)
+    } else {
+        initial_states.probabilities(&DriverSet::new())
+    };
 
-        all_probs = vec![vec![0.0; vars_num]; args.time_steps as usize];
+    let simulate = |time_steps: usize| if sim_args.brute_force {
+        brute_force_simulation(&sync_graph, sim_args, fixes, &initial,
+            &initial_states, time_steps)
+    } else {
+        let trajectory = run_ibmfa(&sync_graph, &pbn_fix, time_steps,
+            sim_args.pulse, Some(initial.clone()));
+        (object!{
+            simulation: trajectory_to_json(&initial, &trajectory, &sync_graph),
+        }, trajectory)
+    };
 
-        let state_space =
-            variations_with_replacement(&[0.0, 1.0], vars_num - fixes.len())
-            .into_iter()
-            .map(|mut variation| {
-                let variables = sync_graph.as_network().variables();
-                for (index, var_id) in variables.enumerate() {
-                    if let Some(value) = fixes.get(&var_id) {
-                        variation.insert(index, if *value { 1.0 } else { 0.0 });
+    let results = match sim_args.until_convergence {
+        None => simulate(time_steps).0,
+        Some(max_steps) => {
+            let limits = ConvergenceLimits {
+                max_steps,
+                tolerance: sim_args.tolerance,
+                max_period: sim_args.max_period,
+            };
+            // The horizon is doubled until the probabilities converge
+            let mut horizon = time_steps.clamp(1, max_steps.max(1));
+            let mut trajectory = Trajectory::new();
+            loop {
+                let mut results = if sim_args.brute_force {
+                    // The exact simulation is not determined by the last
+                    // probabilities, so it starts over
+                    let (results, exact) = simulate(horizon);
+                    trajectory = exact;
+                    results
+                } else {
+                    // IBMFA continues from the last probabilities
+                    let steps = trajectory.len();
+                    let last = trajectory.last().unwrap_or(&initial).clone();
+                    trajectory.extend(run_ibmfa(&sync_graph, &pbn_fix,
+                        horizon - steps,
+                        sim_args.pulse.map(|pulse| pulse.saturating_sub(steps)),
+                        Some(last)));
+                    object!{
+                        simulation: trajectory_to_json(
+                            &initial, &trajectory, &sync_graph),
                     }
-                }
-                variation
-            })
-            .collect::<Vec<_>>();
-
-        let mut remaining_colors = sync_graph.unit_colors().clone();
-        while !remaining_colors.is_empty() {
-            let color = remaining_colors.pick_singleton();
-            remaining_colors = remaining_colors.minus(&color);
-
-            for state in &state_space {
-                let mut iteration_probs = Vec::new();
-                let mut add_probs = |probs: &[f32]| {
-                    iteration_probs.push(probs.to_vec());
                 };
-                ibmfa_entropy(
-                    &sync_graph,
-                    &pbn_fix,
-                    args.time_steps as usize,
-                    false,
-                    None,
-                    Some(&mut add_probs),
-                    Some(state.clone()),
-                    false
-                );
-                for it in 0..args.time_steps as usize {
-                    for i in 0..vars_num {
-                        all_probs[it][i] += iteration_probs[it][i];
-                    }
+                let history = std::iter::once(initial.clone())
+                    .chain(trajectory.iter().cloned())
+                    .collect::<Vec<_>>();
+                let convergence = limits.convergence(
+                    &history, sim_args.pulse.unwrap_or(0));
+                if convergence.is_some() || horizon >= max_steps {
+                    results["convergence"] = convergence_to_json(
+                        convergence, &history, &sync_graph);
+                    break results;
                 }
+                horizon = (2 * horizon).min(max_steps);
             }
-        }
+        },
+    };
+    for (key, value) in results.entries() {
+        json_data[key] = value.clone();
+    }
 
-        for it in 0..args.time_steps as usize {
-            for i in 0..vars_num {
-                all_probs[it][i] /= state_space.len() as f32
-                    * sync_graph.unit_colors().approx_cardinality() as f32;
-            }
+    if let Some(path) = &sim_args.svg {
+        write_simulation_svg(path, &json_data, provenance)
+                .unwrap_or_else(|err| {
+            eprintln!("Err: {err}");
+            process::exit(1);
+        });
+    }
+    print_output(json_data, args, provenance, Some(simulation_table));
+}
+
+/// Simulates every color by brute-force, see `simulation --brute-force`.
+///
+/// Returns the output (the mean trajectory, the distribution across colors,
+/// ...) and the mean trajectory.
+fn brute_force_simulation(
+    sync_graph: &SymbSyncGraph,
+    sim_args: &SimulationArgs,
+    fixes: &DriverSet,
+    initial: &[f32],
+    initial_states: &InitialStates,
+    time_steps: usize,
+) -> (JsonValue, Trajectory) {
+    let context = sync_graph.symbolic_context();
+    let mut results = JsonValue::new_object();
+    let network = sync_graph.as_network();
+    // Functions of variables fixed for the whole simulation do not matter
+    let simulated = network.variables()
+        .filter(|var_id|
+            sim_args.pulse.is_some() || !fixes.contains_key(var_id))
+        .collect::<Vec<_>>();
+    let mut stats = TrajectoryStats::new(sim_args.bins);
+    let mut trajectories = json::Array::new();
+    let mut simulate = |color: &BddValuation, weight: f64| {
+        let trajectory = color_trajectory(sync_graph, color, fixes,
+            sim_args.pulse, time_steps, initial_states)
+            .unwrap_or_else(|err| {
+                eprintln!("Err: {err}");
+                process::exit(1);
+            });
+        stats.add(&trajectory, weight);
+        if sim_args.per_color {
+            trajectories.push(object!{
+                index: trajectories.len(),
+                simulation:
+                    trajectory_to_json(initial, &trajectory, sync_graph),
+            });
+        } else if sim_args.per_group {
+            trajectories.push(object!{
+                colors: weight,
+                functions: simulated.iter()
+                    .map(|var_id| (var_id, &sync_graph
+                        .get_pupdate_functions()[var_id.to_index()]))
+                    .filter(|(_, pupdate_function)|
+                        !pupdate_function.get_parameters().is_empty())
+                    .map(|(var_id, pupdate_function)| object!{
+                        variable: network.get_variable_name(*var_id)
+                            .clone(),
+                        function: dnf_to_str(&function_to_dnf(
+                            &pupdate_function.restricted(color),
+                            context), context),
+                    })
+                    .collect::<json::Array>(),
+                simulation:
+                    trajectory_to_json(initial, &trajectory, sync_graph),
+            });
         }
+    };
 
+    let colors = sync_graph.unit_colors();
+    if let Some(samples) = sim_args.samples {
+        let mut rng = match sim_args.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        for _ in 0..samples {
+            let color = sync_graph.random_color(&colors, &mut rng).unwrap();
+            simulate(&color, 1.0);
+        }
+        results["samples"] = samples.into();
+    } else if sim_args.per_color {
+        for color in sync_graph.iter_colors(&colors) {
+            simulate(&color, 1.0);
+        }
     } else {
-        all_probs = Vec::new();
-        let add_probs = |probs: &[f32]| {
-            all_probs.push(probs.to_vec());
+        let mut groups = 0;
+        for_each_group(sync_graph, &colors, &simulated, &mut |group| {
+            groups += 1;
+            simulate(&sync_graph.nth_color(group, 0).unwrap(),
+                group.approx_cardinality());
+        });
+        results["groups"] = groups.into();
+    }
+
+    results["simulation"] =
+        trajectory_to_json(initial, &stats.mean(), sync_graph);
+    results["envelope"] = object!{
+        min: trajectory_to_json(initial, stats.min(), sync_graph),
+        max: trajectory_to_json(initial, stats.max(), sync_graph),
+    };
+    results["std"] = trajectory_to_json(
+        &vec![0.0; initial.len()], &stats.std(), sync_graph);
+    results["histogram"] = histogram_to_json(initial, &stats, sync_graph);
+    if sim_args.per_color {
+        results["color-trajectories"] = trajectories.into();
+    } else if sim_args.per_group {
+        results["group-trajectories"] = trajectories.into();
+    }
+
+    (results, stats.mean())
+}
+
+/// The detected convergence, or a warning if the horizon was hit.
+fn convergence_to_json(
+    convergence: Option<Convergence>,
+    history: &[Vec<f32>],
+    sync_graph: &SymbSyncGraph,
+) -> JsonValue {
+    let steps = history.len() - 1;
+    let Some(Convergence { step, period }) = convergence else {
+        let warning = format!(
+            "The probabilities did not converge in {steps} steps.");
+        eprintln!("Warning: {warning}");
+        return object!{
+            converged: false,
+            steps: steps,
+            warning: warning,
         };
+    };
+    let network = sync_graph.as_network();
+    let mut cycle = JsonValue::new_object();
+    for var_id in network.variables() {
+        cycle[network.get_variable_name(var_id)] = history[step..step + period]
+            .iter()
+            .map(|probs| probs[var_id.to_index()])
+            .collect::<Vec<_>>()
+            .into();
+    }
+    object!{
+        converged: true,
+        steps: steps,
+        step: step,
+        period: period,
+        cycle: cycle,
+    }
+}
 
-        ibmfa_entropy(
-            &sync_graph,
-            &pbn_fix,
-            args.time_steps as usize,
-            false,
-            None,
-            Some(add_probs),
-            None,
-            false
-        );
+/// Histograms of probabilities of each variable across colors, the initial
+/// one followed by the ones after every step. Each histogram gives
+/// the number of colors (or samples) in the bins.
+fn histogram_to_json(
+    initial: &[f32],
+    stats: &TrajectoryStats,
+    sync_graph: &SymbSyncGraph,
+) -> JsonValue {
+    let mut counts = JsonValue::new_object();
+    for var_id in sync_graph.as_network().variables() {
+        let index = var_id.to_index();
+        let mut initial_counts = vec![0.0; stats.bins()];
+        initial_counts[stats.bin(initial[index])] = stats.weight();
+        counts[sync_graph.as_network().get_variable_name(var_id)] =
+            std::iter::once(initial_counts)
+                .chain(stats.histogram().iter()
+                    .map(|step_counts| step_counts[index].clone()))
+                .collect::<Vec<_>>()
+                .into();
+    }
+    object!{
+        bins: stats.bins(),
+        counts: counts,
     }
+}
 
-    for iteration in all_probs.iter() {
-        for (bdd_var, prob) in
-                context.state_variables().iter().zip(iteration.iter()) {
-            json_data["simulation"][bdd_var_set.name_of(*bdd_var)]
-                .push(*prob).unwrap();
-        }
+/// Probabilities of each variable, the initial one followed by the ones
+/// after every step of `trajectory`.
+fn trajectory_to_json(
+    initial: &[f32],
+    trajectory: &[Vec<f32>],
+    sync_graph: &SymbSyncGraph,
+) -> JsonValue {
+    let mut json_data = JsonValue::new_object();
+    for var_id in sync_graph.as_network().variables() {
+        let index = var_id.to_index();
+        json_data[sync_graph.as_network().get_variable_name(var_id)] =
+            std::iter::once(initial[index])
+                .chain(trajectory.iter().map(|probs| probs[index]))
+                .collect::<Vec<_>>()
+                .into();
     }
+    json_data
+}
+
+/// Runs IBMFA and returns probabilities after every step. The driver-set
+/// of `pbn_fix` is released after `pulse` steps if given.
+fn run_ibmfa(
+    sync_graph: &SymbSyncGraph,
+    pbn_fix: &PBNFix,
+    time_steps: usize,
+    pulse: Option<usize>,
+    initial: Option<Vec<f32>>,
+) -> Vec<Vec<f32>> {
+    let mut all_probs = Vec::new();
+    let add_probs = |probs: &[f32]| {
+        all_probs.push(probs.to_vec());
+    };
 
-    print_json(json_data, args.pretty_json);
+    match pulse {
+        Some(pulse) => {
+            let pulse = time_steps.min(pulse);
+            ibmfa_pulse(sync_graph, pbn_fix, pulse, time_steps - pulse,
+                        None, Some(add_probs), initial, false)
+        },
+        None => ibmfa_entropy(sync_graph, pbn_fix, time_steps, false,
+                              None, Some(add_probs), initial, false),
+    };
+    all_probs
 }
 
 fn print_json(json_data: json::JsonValue, pretty: bool) {
@@ -368,10 +1465,766 @@ fn print_json(json_data: json::JsonValue, pretty: bool) {
     println!("{}", json_str);
 }
 
+// Prints the output in the format of `args`, `table` extracts the table
+// of csv and tsv if the command has one. The json output embeds
+// `provenance`, the resolved spec and the hash of the model, the tables
+// start with it in comment lines.
+fn print_output(
+    json_data: JsonValue,
+    args: &Cli,
+    provenance: &JsonValue,
+    table: Option<fn(&JsonValue) -> Table>,
+) {
+    let table = match (args.format, table) {
+        (DataFormat::Json, _) => {
+            let mut json_data = json_data;
+            json_data["spec"] = provenance["spec"].clone();
+            json_data["model-hash"] = provenance["model_hash"].clone();
+            return print_json(json_data, args.pretty_json);
+        },
+        (_, Some(table)) => table(&json_data),
+        (_, None) => {
+            eprintln!("Err: The command does not output a table, \
+                use '--format json'.");
+            process::exit(1);
+        },
+    };
+    for line in provenance_lines(provenance) {
+        println!("# {line}");
+    }
+    match args.format {
+        DataFormat::Csv => print!("{}", table.to_csv()),
+        _ => print!("{}", table.to_tsv()),
+    }
+}
+
+// The lines of `provenance` in the comments of the outputs other than json
+fn provenance_lines(provenance: &JsonValue) -> Vec<String> {
+    vec![
+        format!("spec: {}", provenance["spec"].dump()),
+        format!("model-hash: {}", json_cell(&provenance["model_hash"])),
+    ]
+}
+
+// Strings are written without quotes, numbers as in json
+fn json_cell(value: &JsonValue) -> String {
+    match value.as_str() {
+        Some(value) => value.to_string(),
+        None => value.dump(),
+    }
+}
+
+/// The probabilities in time, a row per step. The spread across colors
+/// of brute-force simulations is in the columns `{var}_min`, `{var}_max`
+/// and `{var}_std`.
+fn simulation_table(json_data: &JsonValue) -> Table {
+    let variables = json_data["state_variables"].members()
+        .map(json_cell)
+        .collect::<Vec<_>>();
+    let mut columns = vec![("", &json_data["simulation"])];
+    if json_data.has_key("envelope") {
+        columns.push(("_min", &json_data["envelope"]["min"]));
+        columns.push(("_max", &json_data["envelope"]["max"]));
+        columns.push(("_std", &json_data["std"]));
+    }
+    let mut table = Table::new(std::iter::once("step".to_string())
+        .chain(columns.iter()
+            .flat_map(|(suffix, _)| variables.iter()
+                .map(move |var| format!("{var}{suffix}"))))
+        .collect());
+    let steps = json_data["simulation"][&variables[0]].len();
+    table.rows = (0..steps)
+        .map(|step| std::iter::once(step.to_string())
+            .chain(columns.iter()
+                .flat_map(|(_, values)| variables.iter()
+                    .map(move |var| json_cell(&values[var][step]))))
+            .collect())
+        .collect();
+    table
+}
+
+/// The fixed-point attractors, a row per attractor with its colors
+/// and the values of variables.
+fn attractors_table(json_data: &JsonValue) -> Table {
+    let variables = json_data["state_variables"].members()
+        .map(json_cell)
+        .collect::<Vec<_>>();
+    let mut table = Table::new(["attractor", "colors"].into_iter()
+        .map(String::from)
+        .chain(variables)
+        .collect());
+    table.rows = json_data["attractors"].members()
+        .enumerate()
+        .map(|(index, attr)| [index.to_string(), json_cell(&attr["colors"])]
+            .into_iter()
+            .chain(attr["state"].members()
+                .map(|value| if value["value"] == true { "1" } else { "0" }
+                    .to_string()))
+            .collect())
+        .collect();
+    table
+}
+
+/// The ranked measurements, a row per measurement.
+fn ranking_table(json_data: &JsonValue) -> Table {
+    let columns = ["measurement", "cost", "gain"];
+    let mut table = Table::new(columns.map(String::from).to_vec());
+    table.rows = json_data["ranking"].members()
+        .map(|ranked| columns.iter()
+            .map(|column| json_cell(&ranked[*column]))
+            .collect())
+        .collect();
+    table
+}
+
+/// The admissible update functions, a row per function with its colors.
+fn functions_table(json_data: &JsonValue) -> Table {
+    let mut table = Table::new(["variable", "function", "colors"]
+        .map(String::from).to_vec());
+    table.rows = json_data["functions"].members()
+        .flat_map(|var| var["functions"].members()
+            .map(|function| vec![json_cell(&var["variable"]),
+                json_cell(&function["function"]),
+                json_cell(&function["colors"])]))
+        .collect();
+    table
+}
+
+/// Plots the probabilities of the output of `simulation` as SVG.
+fn write_simulation_svg(
+    path: &Path,
+    json_data: &JsonValue,
+    provenance: &JsonValue,
+) -> Result<(), String> {
+    let values = |json: &JsonValue| json.members()
+        .map(|value| value.as_f32().unwrap_or(0.0))
+        .collect::<Vec<_>>();
+    let lines = json_data["state_variables"].members()
+        .map(json_cell)
+        .map(|var| PlotLine {
+            values: values(&json_data["simulation"][&var]),
+            envelope: json_data.has_key("envelope").then(|| (
+                values(&json_data["envelope"]["min"][&var]),
+                values(&json_data["envelope"]["max"][&var]))),
+            std: json_data.has_key("std")
+                .then(|| values(&json_data["std"][&var])),
+            name: var,
+        })
+        .collect::<Vec<_>>();
+    let svg = xml_comment(&provenance_lines(provenance)) + "\n"
+        + &line_plot_svg(&lines);
+    fs::write(path, svg).map_err(|err| format!(
+        "Cannot write the plot '{}', err: {err}", path.display()))
+}
+
+fn parse_costs(costs: &[String], sync_graph: &SymbSyncGraph)
+-> Result<HashMap<Measurement, f64>, String> {
+    costs.iter()
+        .map(|cost_str| {
+            let (name, cost) = cost_str.rsplit_once(':')
+                .ok_or(format!("Invalid cost '{cost_str}'. \
+                    Expected format '{{name}}:{{cost}}'."))?;
+            let cost = cost.parse::<f64>()
+                .map_err(|_| format!("Invalid cost '{cost_str}'. \
+                    Expected a number, found '{cost}'."))?;
+            Ok((Measurement::from_name(name, sync_graph)?, cost))
+        })
+        .collect()
+}
+
+fn main_design(
+    args: &Cli,
+    provenance: &JsonValue,
+    design_args: &DesignArgs,
+) {
+    let model = load_model(args.path());
+    let sync_graph = SymbSyncGraph::new(model);
+    let context = sync_graph.symbolic_context();
+    let costs = parse_costs(&design_args.cost, &sync_graph)
+        .unwrap_or_else(|err| {
+            eprintln!("Err: {err}");
+            process::exit(1);
+        });
+
+    let target = match design_args.target {
+        DesignTarget::Landscape =>
+            fixed_point_classes(&sync_graph, &sync_graph.unit_colors())
+                .into_iter()
+                .map(|(colors, _)| colors)
+                .collect::<Vec<_>>(),
+        DesignTarget::DriverSet => {
+            let mut attrs = sync_graph.fixed_point_attractors();
+            attrs.sort_by_key(|attr| Reverse(attr.exact_cardinality()));
+            let Some(attr) = design_args.attractor
+                    .and_then(|index| attrs.get(index)) else {
+                eprintln!("Err: Expected '--attractor' \
+                    with an index less than {}.", attrs.len());
+                process::exit(1);
+            };
+            let driver_sets = colors_partition(
+                &sync_graph, args.time_steps, true,
+                (&attr.vertices(), &attr.colors()), false);
+            driver_set_classes(&sync_graph, &driver_sets)
+        },
+    };
+    let colors = target.iter()
+        .fold(sync_graph.empty_colored_vertices().colors(),
+            |acc, class| acc.union(class));
+
+    let measurements = all_measurements(
+        &sync_graph, &colors,
+        !design_args.no_parameters, !design_args.no_perturbations);
+    let ranked = rank_measurements(&sync_graph, &target, &measurements, &costs);
+    let plan = sequential_plan(&target, &ranked, design_args.plan);
+
+    let mut json_data = json::JsonValue::new_object();
+    add_state_variables(context, &mut json_data);
+    json_data["colors"] = colors.approx_cardinality().into();
+    json_data["target_classes"] = target.len().into();
+    json_data["target_entropy"] = entropy(&target).into();
+
+    json_data["ranking"] = JsonValue::Array(ranked.iter()
+        .take(design_args.top)
+        .map(|ranked| object!{
+            measurement: ranked.measurement.name(&sync_graph),
+            cost: ranked.cost,
+            gain: ranked.gain,
+            outcomes: ranked.outcomes.iter()
+                .map(|(outcome, fixed_points)| object!{
+                    colors: outcome.approx_cardinality(),
+                    fixed_points: match fixed_points {
+                        Some(vertices) => vertices_to_json(vertices, &sync_graph),
+                        None => JsonValue::Null,
+                    },
+                })
+                .collect::<json::Array>(),
+        })
+        .collect::<json::Array>());
+
+    json_data["plan"] = JsonValue::Array(plan.iter()
+        .map(|step| object!{
+            measurement: step.measurement.name(&sync_graph),
+            cost: step.cost,
+            gain: step.gain,
+            entropy: step.entropy,
+        })
+        .collect::<json::Array>());
+
+    print_output(json_data, args, provenance, Some(ranking_table));
+}
+
+fn main_evaluate(
+    args: &Cli,
+    provenance: &JsonValue,
+    evaluate_args: &EvaluateArgs,
+) {
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+    let model = load_model(args.path());
+    let sync_graph = SymbSyncGraph::new(model);
+    let context = sync_graph.symbolic_context();
+
+    let tree_string = fs::read_to_string(&evaluate_args.tree)
+        .unwrap_or_else(|err| {
+            eprintln!("Cannot read the file, err: {}", err);
+            process::exit(1);
+        });
+    let tree_json = json::parse(&tree_string)
+        .unwrap_or_else(|err| exit_on_err(err.to_string()));
+    let (tree, tree_colors) = tree_from_json(&tree_json, &sync_graph,
+            &|label| driver_set_from_json(label, &sync_graph))
+        .unwrap_or_else(|err| exit_on_err(err));
+
+    // The color of the network and the parameter fixes
+    let mut colors = match &evaluate_args.network {
+        Some(path) => sync_graph.instance_colors(&load_model(path))
+            .unwrap_or_else(|err| exit_on_err(err)),
+        None => sync_graph.unit_colors(),
+    };
+    let parameters =
+        parse_parameter_fixes(&evaluate_args.parameter, &sync_graph)
+        .unwrap_or_else(|err| exit_on_err(err));
+    colors = colors.copy(colors.as_bdd().select(&parameters));
+    if colors.is_empty() {
+        exit_on_err(
+            "No color satisfies the network and the parameters.".into());
+    }
+
+    // E.g. the attractor of the tree does not exist in the other colors
+    let outside = colors.minus(&tree_colors);
+    let colors = colors.intersect(&tree_colors);
+    let driver_set = if colors.is_empty() {
+        None
+    } else {
+        tree.evaluate(colors.as_bdd())
+    };
+
+    let json_data = object!{
+        colors: colors.approx_cardinality(),
+        outside_colors: outside.approx_cardinality(),
+        driver_set: match driver_set {
+            Some(driver_set) => driver_set_to_json(driver_set, context),
+            None => JsonValue::Null,
+        },
+        attractor: tree_json["attractor"].clone(),
+    };
+    print_output(json_data, args, provenance, None);
+}
+
+fn main_convert(
+    args: &Cli,
+    provenance: &JsonValue,
+    convert_args: &ConvertArgs,
+) {
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+    // The model is written as it is, without the self-regulations of inputs
+    let model = read_model_file(args.path())
+        .unwrap_or_else(|err| exit_on_err(err));
+    write_output_model(&model, &convert_args.output, convert_args.format,
+            provenance)
+        .unwrap_or_else(|err| exit_on_err(err));
+}
+
+fn main_instantiate(
+    args: &Cli,
+    provenance: &JsonValue,
+    instantiate_args: &InstantiateArgs,
+) {
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+    let model = load_model(args.path());
+    let sync_graph = SymbSyncGraph::new(model);
+
+    let parameters =
+        parse_parameter_fixes(&instantiate_args.parameter, &sync_graph)
+        .unwrap_or_else(|err| exit_on_err(err));
+    let colors = sync_graph.unit_colors();
+    let colors = colors.copy(colors.as_bdd().select(&parameters));
+    let color = if instantiate_args.random {
+        let mut rng = match instantiate_args.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        sync_graph.random_color(&colors, &mut rng)
+    } else {
+        sync_graph.nth_color(&colors, instantiate_args.index)
+    };
+    let color = color.unwrap_or_else(|| exit_on_err(format!(
+        "There is no such color, {} colors satisfy the parameters.",
+        colors.approx_cardinality())));
+
+    write_output_model(&sync_graph.instantiate(&color),
+            &instantiate_args.output, instantiate_args.format, provenance)
+        .unwrap_or_else(|err| exit_on_err(err));
+}
+
+fn main_parametrizations(
+    args: &Cli,
+    provenance: &JsonValue,
+    par_args: &ParametrizationsArgs,
+) {
+    let model = load_model(args.path());
+    let sync_graph = SymbSyncGraph::new(model);
+    let context = sync_graph.symbolic_context();
+    let network = sync_graph.as_network();
+
+    let parameters =
+        parse_parameter_fixes(&par_args.parameter, &sync_graph)
+        .unwrap_or_else(|err| {
+            eprintln!("Err: {err}");
+            process::exit(1);
+        });
+    let colors = sync_graph.unit_colors();
+    let colors = colors.copy(colors.as_bdd().select(&parameters));
+    let function_to_json = |function: &Bdd| JsonValue::String(
+        dnf_to_str(&function_to_dnf(function, context), context));
+
+    let mut json_data = object!{
+        colors: colors.approx_cardinality(),
+        functions: network.variables()
+            .map(|var_id| object!{
+                variable: network.get_variable_name(var_id).clone(),
+                regulators: network.regulators(var_id).iter()
+                    .map(|regulator| network.get_variable_name(*regulator)
+                        .clone())
+                    .collect::<Vec<_>>(),
+                functions: sync_graph.function_classes(var_id, &colors)
+                    .iter()
+                    .map(|(function, function_colors)| object!{
+                        function: function_to_json(function),
+                        colors: function_colors.approx_cardinality(),
+                    })
+                    .collect::<json::Array>(),
+            })
+            .collect::<json::Array>(),
+    };
+
+    if par_args.list_colors {
+        // Only the functions of parametrized variables differ
+        let parametrized = network.variables()
+            .zip(sync_graph.get_pupdate_functions())
+            .filter(|(_, pupdate_function)|
+                !pupdate_function.get_parameters().is_empty())
+            .collect::<Vec<_>>();
+        json_data["color-list"] = (par_args.offset..)
+            .take(par_args.limit)
+            .map_while(|index| Some((index,
+                sync_graph.nth_color(&colors, index)?)))
+            .map(|(index, color)| object!{
+                index: index,
+                parameters: context.parameter_variables().iter()
+                    .map(|bdd_var| object!{
+                        variable: parameter_name(*bdd_var, &sync_graph),
+                        value: color[*bdd_var],
+                    })
+                    .collect::<json::Array>(),
+                functions: parametrized.iter()
+                    .map(|(var_id, pupdate_function)| object!{
+                        variable: network.get_variable_name(*var_id).clone(),
+                        function: function_to_json(
+                            &pupdate_function.restricted(&color)),
+                    })
+                    .collect::<json::Array>(),
+            })
+            .collect::<json::Array>()
+            .into();
+    }
+
+    print_output(json_data, args, provenance, Some(functions_table));
+}
+
+// Writes `model` to `output`, or to the standard output (.aeon by default),
+// starting with `provenance` in comment lines
+fn write_output_model(
+    model: &BooleanNetwork,
+    output: &Option<PathBuf>,
+    format: Option<OutputFormat>,
+    provenance: &JsonValue,
+) -> Result<(), String> {
+    let format = match (output, format.map(ModelFormat::from)) {
+        (_, Some(format)) => format,
+        (Some(path), None) => ModelFormat::from_path(path).ok_or(format!(
+            "Unknown model format of '{}'.", path.display()))?,
+        (None, None) => ModelFormat::Aeon,
+    };
+    let model_str = comment_model(&write_model(model, format)?, format,
+        &provenance_lines(provenance));
+    match output {
+        Some(path) => fs::write(path, model_str)
+            .map_err(|err| format!("Cannot write the file, err: {err}")),
+        None => {
+            println!("{model_str}");
+            Ok(())
+        },
+    }
+}
+
+fn main_batch(
+    args: &Cli,
+    provenance: &JsonValue,
+    batch_args: &BatchArgs,
+) {
+    let models = batch_models(args.path()).unwrap_or_else(|err| {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    });
+    let exe = std::env::current_exe().unwrap_or_else(|err| {
+        eprintln!("Err: Cannot find the executable, err: {err}");
+        process::exit(1);
+    });
+
+    let global_args = ["-t".to_string(), args.time_steps.to_string()];
+    let json_data = run_batch(&exe, &models, &global_args,
+        &batch_args.analysis, batch_args.timeout);
+    for report in json_data["reports"].members()
+            .filter(|report| !report["error"].is_null()) {
+        eprintln!("Failed {}: {}", report["model"], report["error"]);
+    }
+    print_output(json_data, args, provenance, Some(batch_table));
+}
+
+/// The summary of models, a row per model.
+fn batch_table(json_data: &JsonValue) -> Table {
+    let columns = ["model", "variables", "colors", "attractors",
+        "driver_set_sizes", "seconds", "peak_memory_kb", "error"];
+    let mut table = Table::new(columns.map(String::from).to_vec());
+    table.rows = json_data["reports"].members()
+        .map(|report| columns.iter()
+            .map(|column| match &report[*column] {
+                JsonValue::Null => String::new(),
+                JsonValue::Array(values) => values.iter()
+                    .map(json_cell)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                value => json_cell(value),
+            })
+            .collect())
+        .collect();
+    table
+}
+
+// The arguments of the command line given by `spec`, see `--spec`,
+// the relative paths are resolved against the directory `dir` of the spec
+fn spec_args(spec: &JsonValue, dir: &Path) -> Result<Vec<String>, String> {
+    let cli = Cli::command();
+    let name = spec["command"].as_str()
+        .ok_or("The spec does not contain 'command'.")?;
+    let command = cli.find_subcommand(name)
+        .ok_or(format!("Unknown command '{name}' in the spec."))?;
+    let model = spec["model"].as_str()
+        .ok_or("The spec does not contain 'model'.")?;
+    match &spec["semantics"] {
+        JsonValue::Null => {},
+        semantics if semantics == "synchronous" => {},
+        semantics => return Err(format!("Unsupported semantics \
+            '{semantics}', only 'synchronous'.")),
+    }
+
+    let mut args = vec![cli.get_name().to_string()];
+    let mut command_args = vec![name.to_string()];
+    for (key, value) in spec.entries() {
+        match key {
+            "model" | "command" | "semantics" | "arguments" => {},
+            _ if value.is_object() => for (key, value) in value.entries() {
+                push_spec_arg(&mut command_args, command, key, value, dir)?;
+            },
+            _ => push_spec_arg(&mut args, &cli, key, value, dir)?,
+        }
+    }
+    // The positional arguments before `--` are given in order
+    let mut positionals = command.get_positionals()
+        .filter(|arg| !arg.is_last_set());
+    let mut is_last = false;
+    for value in spec["arguments"].members() {
+        let value = json_cell(value);
+        is_last |= value == "--";
+        let is_path = !is_last && positionals.next()
+            .is_some_and(|arg| arg.get_value_hint() == ValueHint::AnyPath);
+        command_args.push(match is_path {
+            true => dir.join(value).display().to_string(),
+            false => value,
+        });
+    }
+    args.push(dir.join(model).display().to_string());
+    args.extend(command_args);
+    Ok(args)
+}
+
+// Pushes the option `key` of `command` with the values of `value`,
+// a flag is pushed if true, a path is resolved against `dir`
+fn push_spec_arg(
+    args: &mut Vec<String>,
+    command: &clap::Command,
+    key: &str,
+    value: &JsonValue,
+    dir: &Path,
+) -> Result<(), String> {
+    let arg = command.get_arguments()
+        .filter(|arg| arg.get_id() != "spec")
+        .find(|arg| arg.get_id() == key && arg.get_long().is_some())
+        .ok_or(format!("Unknown argument '{key}' of '{}' in the spec.",
+            command.get_name()))?;
+    let option = format!("--{}", arg.get_long().unwrap());
+    let values = match value {
+        JsonValue::Array(values) => values.iter().collect(),
+        value => vec![value],
+    };
+    for value in values {
+        match value {
+            JsonValue::Null => {},
+            JsonValue::Boolean(flag) if !arg.get_action().takes_values() => {
+                if *flag {
+                    args.push(option.clone());
+                }
+            },
+            JsonValue::Object(_) | JsonValue::Array(_) =>
+                return Err(format!("Invalid value of '{key}' in the spec.")),
+            // E.g. the defaults of a resolved spec, some options require
+            // others when given
+            value if is_default(arg, &json_cell(value)) => {},
+            value if arg.get_value_hint() == ValueHint::AnyPath => {
+                args.push(option.clone());
+                args.push(dir.join(json_cell(value)).display().to_string());
+            },
+            value => {
+                args.push(option.clone());
+                args.push(json_cell(value));
+            },
+        }
+    }
+    Ok(())
+}
+
+fn is_default(arg: &clap::Arg, value: &str) -> bool {
+    let number = |value: &str| value.parse::<f64>().ok();
+    match arg.get_default_values() {
+        [default] => {
+            let default = default.to_string_lossy();
+            default == value
+                || number(&default).is_some_and(|default|
+                    Some(default) == number(value))
+        },
+        _ => false,
+    }
+}
+
+// The spec of the experiment of `matches`, with the defaults. It contains
+// the arguments of the command in the table named by the command.
+// The paths are absolute, so the spec may be saved anywhere.
+fn resolved_spec(matches: &clap::ArgMatches) -> JsonValue {
+    let cli = Cli::command();
+    let mut spec = object!{
+        model: matches.get_one::<PathBuf>("path")
+            .map(|path| absolute_path(&path.to_string_lossy())),
+        command: matches.subcommand_name(),
+        semantics: "synchronous",
+    };
+    add_resolved_args(&mut spec, &cli, matches);
+    if let Some((name, command_matches)) = matches.subcommand() {
+        let command = cli.find_subcommand(name).unwrap();
+        let mut command_args = JsonValue::new_object();
+        add_resolved_args(&mut command_args, command, command_matches);
+        spec[name] = command_args;
+
+        let mut positional = Vec::new();
+        for arg in command.get_positionals() {
+            let values = command_matches.get_raw(arg.get_id().as_str())
+                .into_iter()
+                .flatten()
+                .map(|value| resolved_value(arg, &value.to_string_lossy()))
+                .collect::<Vec<_>>();
+            if arg.is_last_set() && !values.is_empty() {
+                positional.push("--".to_string());
+            }
+            positional.extend(values);
+        }
+        if !positional.is_empty() {
+            spec["arguments"] = positional.into();
+        }
+    }
+    spec
+}
+
+// Adds the options of `command` given (or defaulted) in `matches`, numbers
+// as numbers, repeated options as arrays
+fn add_resolved_args(
+    spec: &mut JsonValue,
+    command: &clap::Command,
+    matches: &clap::ArgMatches,
+) {
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if arg.is_positional() || ["help", "version", "spec"].contains(&id) {
+            continue;
+        }
+        spec[id] = match arg.get_action() {
+            ArgAction::SetTrue => matches.get_flag(id).into(),
+            action => {
+                let Some(values) = matches.get_raw(id) else {
+                    continue;
+                };
+                let values = values
+                    .map(|value| resolved_value(arg, &value.to_string_lossy()))
+                    .collect::<Vec<_>>();
+                match action {
+                    ArgAction::Append => values.into(),
+                    _ => match values[0].parse::<f64>() {
+                        Ok(number) if number.is_finite() => number.into(),
+                        _ => values[0].clone().into(),
+                    },
+                }
+            },
+        };
+    }
+}
+
+// The value of `arg` in a resolved spec, paths are absolute
+fn resolved_value(arg: &clap::Arg, value: &str) -> String {
+    match arg.get_value_hint() {
+        ValueHint::AnyPath => absolute_path(value),
+        _ => value.to_string(),
+    }
+}
+
+fn absolute_path(path: &str) -> String {
+    std::path::absolute(path)
+        .map_or(path.to_string(), |path| path.display().to_string())
+}
+
 fn main() {
-    let args = Cli::parse();
-    match &args.command {
-        Commands::Analysis(driver_args) => main_analysis(&args, driver_args),
-        Commands::Simulation(sim_args) => main_simulation(&args, sim_args),
+    let exit_on_err = |err: String| -> ! {
+        eprintln!("Err: {err}");
+        process::exit(1);
+    };
+    // `--spec` lifts the required path, but not the command
+    let mut matches = Cli::command().get_matches();
+    if let Some(path) = matches.get_one::<PathBuf>("spec") {
+        if matches.subcommand().is_some() {
+            Cli::command().error(ErrorKind::ArgumentConflict,
+                    "The command is given by the spec, see '--spec'.")
+                .exit();
+        }
+        let mut spec = read_spec(path).unwrap_or_else(|err| exit_on_err(err));
+        // The format of the output given on the command line
+        if matches.value_source("format") == Some(ValueSource::CommandLine) {
+            spec["format"] = matches.get_one::<DataFormat>("format").unwrap()
+                .to_possible_value().unwrap().get_name().into();
+        }
+        if matches.get_flag("pretty_json") {
+            spec["pretty_json"] = true.into();
+        }
+        let dir = std::path::absolute(path).ok()
+            .and_then(|path| Some(path.parent()?.to_path_buf()))
+            .unwrap_or_default();
+        let spec_args = spec_args(&spec, &dir)
+            .unwrap_or_else(|err| exit_on_err(err));
+        matches = Cli::command().try_get_matches_from(spec_args)
+            .unwrap_or_else(|err| err.exit());
+    } else if matches.subcommand().is_none() {
+        Cli::command().error(ErrorKind::MissingSubcommand,
+                "A command is required, see '--help'.")
+            .exit();
+    }
+    let args = Cli::from_arg_matches(&matches)
+        .unwrap_or_else(|err| err.exit());
+    let provenance = object!{
+        spec: resolved_spec(&matches),
+        model_hash: if args.path().is_file() {
+            model_hash(args.path()).unwrap_or_else(|err| exit_on_err(err))
+                .into()
+        } else {
+            JsonValue::Null
+        },
+    };
+
+    match args.command.as_ref().unwrap() {
+        Commands::Analysis(driver_args) =>
+            main_analysis(&args, &provenance, driver_args),
+        Commands::Simulation(sim_args) =>
+            main_simulation(&args, &provenance, sim_args),
+        Commands::Design(design_args) =>
+            main_design(&args, &provenance, design_args),
+        Commands::Evaluate(evaluate_args) =>
+            main_evaluate(&args, &provenance, evaluate_args),
+        Commands::Convert(convert_args) =>
+            main_convert(&args, &provenance, convert_args),
+        Commands::Instantiate(instantiate_args) =>
+            main_instantiate(&args, &provenance, instantiate_args),
+        Commands::Parametrizations(par_args) =>
+            main_parametrizations(&args, &provenance, par_args),
+        Commands::Batch(batch_args) =>
+            main_batch(&args, &provenance, batch_args),
+    }
+    if args.peak_memory {
+        if let Some(memory) = peak_memory(process::id()) {
+            eprintln!("Peak memory: {memory} kB");
+        }
     }
 }
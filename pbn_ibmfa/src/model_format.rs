@@ -0,0 +1,284 @@
+use std::path::Path;
+
+use biodivine_lib_param_bn::BooleanNetwork;
+
+pub mod sbml;
+pub mod rules;
+
+use rules::RuleSyntax;
+use crate::output::xml_comment;
+
+
+/*******************************************************************************
+ * Model formats
+ * =============
+ * Models are read from .aeon, SBML-qual, .bnet or the rules of CANA,
+ * see `sbml` and `rules` for the supported subsets. Only fully specified
+ * networks are written to the formats other than .aeon, other tools do not
+ * understand the parameters. Inputs without regulators may stay without
+ * update functions, they are constant in each state.
+ ******************************************************************************/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    Aeon,
+    Sbml,
+    Bnet,
+    /// The rules `A *= B and not C` of CANA, BooleanNet and PyBoolNet.
+    Cana,
+}
+
+impl ModelFormat {
+    /// Detects the format by the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<ModelFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "aeon" => Some(ModelFormat::Aeon),
+            "sbml" | "xml" => Some(ModelFormat::Sbml),
+            "bnet" => Some(ModelFormat::Bnet),
+            "cana" | "booleannet" => Some(ModelFormat::Cana),
+            _ => None,
+        }
+    }
+
+    /// Detects the format by the content of a model file. Any XML document
+    /// is expected to be SBML-qual.
+    pub fn from_content(model_str: &str) -> ModelFormat {
+        // The first line which is not a comment
+        let line = model_str.lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        let is_name = |name: &str| !name.trim().is_empty() && name.trim()
+            .chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if line.starts_with('<') {
+            ModelFormat::Sbml
+        } else if line.contains("*=") {
+            ModelFormat::Cana
+        } else if line.split_once(',').is_some_and(|(name, _)| is_name(name)) {
+            ModelFormat::Bnet
+        } else {
+            ModelFormat::Aeon
+        }
+    }
+}
+
+/// Reads a model in `format`, or in the format detected by the content.
+pub fn read_model(model_str: &str, format: Option<ModelFormat>)
+-> Result<BooleanNetwork, String> {
+    match format.unwrap_or_else(|| ModelFormat::from_content(model_str)) {
+        ModelFormat::Aeon => BooleanNetwork::try_from(model_str),
+        ModelFormat::Sbml => sbml::read_sbml(model_str),
+        ModelFormat::Bnet => rules::read_rules(model_str, RuleSyntax::Bnet),
+        ModelFormat::Cana => rules::read_rules(model_str, RuleSyntax::Cana),
+    }
+}
+
+/// Reads the model from a file, the format is given by the extension
+/// or detected by the content.
+pub fn read_model_file(path: &Path) -> Result<BooleanNetwork, String> {
+    let model_str = std::fs::read_to_string(path)
+        .map_err(|err| format!("Cannot read the file, err: {err}"))?;
+    read_model(&model_str, ModelFormat::from_path(path))
+}
+
+/// Writes `model` in `format`.
+///
+/// Fails for formats other than .aeon if `model` is not fully specified.
+pub fn write_model(model: &BooleanNetwork, format: ModelFormat)
+-> Result<String, String> {
+    if format != ModelFormat::Aeon {
+        check_fully_specified(model)?;
+    }
+    match format {
+        ModelFormat::Aeon => Ok(model.to_string()),
+        ModelFormat::Sbml => Ok(model.to_sbml(None)),
+        ModelFormat::Bnet => rules::write_rules(model, RuleSyntax::Bnet),
+        ModelFormat::Cana => rules::write_rules(model, RuleSyntax::Cana),
+    }
+}
+
+/// Prefixes `model_str` written in `format` by a comment of the lines,
+/// after the XML declaration of SBML.
+pub fn comment_model(model_str: &str, format: ModelFormat, lines: &[String])
+-> String {
+    match format {
+        ModelFormat::Sbml => {
+            let split = model_str.strip_prefix("<?xml")
+                .and_then(|rest| rest.find("?>"))
+                .map_or(0, |end| "<?xml".len() + end + "?>".len());
+            let (declaration, rest) = model_str.split_at(split);
+            format!("{declaration}{}{rest}", xml_comment(lines))
+        },
+        _ => lines.iter()
+            .map(|line| format!("# {line}\n"))
+            .collect::<String>() + model_str,
+    }
+}
+
+/// Writes `model` to a file, the format is given by the extension.
+pub fn write_model_file(model: &BooleanNetwork, path: &Path)
+-> Result<(), String> {
+    let format = ModelFormat::from_path(path).ok_or(format!(
+        "Unknown model format of '{}'.", path.display()))?;
+    std::fs::write(path, write_model(model, format)?)
+        .map_err(|err| format!("Cannot write the file, err: {err}"))
+}
+
+fn check_fully_specified(model: &BooleanNetwork) -> Result<(), String> {
+    // Inputs without regulators stay without update functions
+    if let Some(var_id) = model.variables()
+            .find(|var_id| model.get_update_function(*var_id).is_none()
+                && !model.regulators(*var_id).is_empty()) {
+        return Err(format!("The update function of '{}' is not specified.",
+            model.get_variable_name(var_id)));
+    }
+    if let Some(parameter) = model.parameters().next() {
+        return Err(format!("The model contains parameter '{}'.",
+            model[parameter].get_name()));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    use super::{ModelFormat, comment_model, read_model, read_model_file,
+        write_model};
+
+    fn models() -> Vec<PathBuf> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../models");
+        let mut models = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| ModelFormat::from_path(path).is_some())
+            .collect::<Vec<_>>();
+        models.sort();
+        models
+    }
+
+    // The fully specified models, the others cannot be converted
+    fn instances() -> Vec<(PathBuf, BooleanNetwork)> {
+        let instances = models().into_iter()
+            .map(|path| {
+                let model = read_model_file(&path).unwrap();
+                (path, model)
+            })
+            .filter(|(_, model)| write_model(model, ModelFormat::Bnet).is_ok())
+            .collect::<Vec<_>>();
+        assert!(!instances.is_empty());
+        instances
+    }
+
+    fn convert(model: &BooleanNetwork, format: ModelFormat)
+    -> BooleanNetwork {
+        let model_str = write_model(model, format).unwrap();
+        read_model(&model_str, Some(format)).unwrap()
+    }
+
+    // The regulators and the truth table of the update function of each
+    // variable by names, the ids may differ between formats. An input
+    // without a function is written as the identity.
+    fn tables(model: &BooleanNetwork)
+    -> HashMap<String, (Vec<String>, Option<Vec<bool>>)> {
+        model.variables()
+            .map(|var_id| {
+                let mut regulators = model.regulators(var_id);
+                regulators.sort_by_key(|regulator|
+                    model.get_variable_name(*regulator).clone());
+                let table = model.get_update_function(var_id).as_ref()
+                    .map(|function| (0..1usize << regulators.len())
+                        .map(|row| {
+                            let values = regulators.iter().enumerate()
+                                .map(|(i, regulator)|
+                                    (*regulator, row >> i & 1 == 1))
+                                .collect();
+                            function.evaluate(&values).unwrap()
+                        })
+                        .collect());
+                let regulators: Vec<String> = regulators.iter()
+                    .map(|regulator| model.get_variable_name(*regulator))
+                    .cloned()
+                    .collect();
+                let name = model.get_variable_name(var_id).clone();
+                match table {
+                    None if regulators.is_empty() =>
+                        (name.clone(), (vec![name], Some(vec![false, true]))),
+                    _ => (name, (regulators, table)),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aeon_sbml_aeon() {
+        for (path, model) in instances() {
+            let converted = convert(&convert(&model, ModelFormat::Sbml),
+                ModelFormat::Aeon);
+            assert_eq!(tables(&model), tables(&converted), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn aeon_bnet_cana_aeon() {
+        for (path, model) in instances() {
+            let converted = convert(&model, ModelFormat::Bnet);
+            let converted = convert(&converted, ModelFormat::Cana);
+            let converted = convert(&converted, ModelFormat::Aeon);
+            assert_eq!(tables(&model), tables(&converted), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn commented_models() {
+        let lines = [
+            r#"spec: {"arguments":["--","-t"],"model":"a.aeon"}"#.to_string(),
+            "model-hash: 0123abcd".to_string(),
+        ];
+        let formats = [ModelFormat::Aeon, ModelFormat::Sbml,
+            ModelFormat::Bnet, ModelFormat::Cana];
+        for (path, model) in instances() {
+            for format in formats {
+                let model_str = comment_model(
+                    &write_model(&model, format).unwrap(), format, &lines);
+                for format_opt in [Some(format), None] {
+                    let commented = read_model(&model_str, format_opt)
+                        .unwrap();
+                    assert_eq!(tables(&model), tables(&commented),
+                        "{path:?} {format:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_models() {
+        let models = [
+            (ModelFormat::Aeon, "a -> b\n$b: a &"),
+            (ModelFormat::Aeon, "a -> b\n$b: (a"),
+            (ModelFormat::Aeon, "a -> b\n$c: a"),
+            (ModelFormat::Sbml, ""),
+            (ModelFormat::Sbml, "<sbml><model></sbml>"),
+            (ModelFormat::Sbml, "<notsbml/>"),
+            (ModelFormat::Sbml, "<sbml/>"),
+            (ModelFormat::Bnet, "targets, factors\na, b &"),
+            (ModelFormat::Bnet, "targets, factors\na, (b"),
+            (ModelFormat::Bnet, "targets, factors\na, b c"),
+            (ModelFormat::Bnet, "targets, factors\na"),
+            (ModelFormat::Bnet, "targets, factors\na, !"),
+            (ModelFormat::Bnet, "targets, factors\na, a\na, !a"),
+            (ModelFormat::Cana, "a *= b and"),
+            (ModelFormat::Cana, "a *= (b or a"),
+            (ModelFormat::Cana, "a *= b )"),
+            (ModelFormat::Cana, "a = b"),
+            (ModelFormat::Cana, "and *= a"),
+        ];
+        for (format, model_str) in models {
+            assert!(read_model(model_str, Some(format)).is_err(),
+                "{format:?}: {model_str:?}");
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use biodivine_lib_bdd::boolean_expression::BooleanExpression;
+use biodivine_lib_param_bn::{BinaryOp, BooleanNetwork, FnUpdate,
+    RegulatoryGraph, VariableId};
+
+use crate::expression::{Token, parse_tokens};
+
+
+/*******************************************************************************
+ * Rule formats
+ * ============
+ * Both .bnet (BoolNet, PyBoolNet) and the rules of CANA or BooleanNet
+ * assign a Boolean expression to each variable on a separate line,
+ * `A, B & !C` and `A *= B and not C`. The reader accepts the operators of
+ * both syntaxes and the constants `0`, `1`, `true` and `false`, comments
+ * start with `#`. Variables without a rule are inputs without an update
+ * function. The regulatory graph is inferred from the update functions.
+ ******************************************************************************/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleSyntax {
+    Bnet,
+    Cana,
+}
+
+impl RuleSyntax {
+    fn assignment(self) -> &'static str {
+        match self {
+            RuleSyntax::Bnet => ",",
+            RuleSyntax::Cana => " *=",
+        }
+    }
+}
+
+/// Reads a network from the rules in `syntax`.
+pub fn read_rules(model_str: &str, syntax: RuleSyntax)
+-> Result<BooleanNetwork, String> {
+    let mut rules: Vec<(String, Vec<Token>)> = Vec::new();
+    for line in model_str.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty()
+                || (syntax == RuleSyntax::Bnet && line.replace(' ', "")
+                    .to_lowercase().starts_with("targets,factors")) {
+            continue;
+        }
+        let (target, expression) = line.split_once(syntax.assignment().trim())
+            .ok_or(format!("Expected a rule, found '{line}'."))?;
+        let target = target.trim();
+        if !is_valid_name(target) {
+            return Err(format!("Invalid variable name '{target}'."));
+        }
+        if rules.iter().any(|(name, _)| name == target) {
+            return Err(format!("Multiple rules of '{target}'."));
+        }
+        rules.push((target.to_string(), tokenize(expression)?));
+    }
+
+    // Targets first, then the inputs without rules
+    let mut names = rules.iter()
+        .map(|(target, _)| target.clone())
+        .collect::<Vec<_>>();
+    for (_, tokens) in &rules {
+        for token in tokens {
+            if let Token::Name(name) = token {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+
+    let mut graph = RegulatoryGraph::new(names);
+    for (target, tokens) in &rules {
+        for token in tokens {
+            if let Token::Name(name) = token {
+                let regulator = graph.find_variable(name).unwrap();
+                let target_id = graph.find_variable(target).unwrap();
+                if graph.find_regulation(regulator, target_id).is_none() {
+                    graph.add_regulation(name, target, false, None)?;
+                }
+            }
+        }
+    }
+    let mut model = BooleanNetwork::new(graph);
+    for (target, tokens) in &rules {
+        let function = parse_tokens(tokens)
+            .map(|expression| expression_to_function(&expression, &model))
+            .map_err(|err| format!("Invalid rule of '{target}', err: {err}"))?;
+        let target = model.as_graph().find_variable(target).unwrap();
+        model.add_update_function(target, function)?;
+    }
+    model.infer_valid_graph()
+}
+
+/// Writes the rules of `model` in `syntax`.
+///
+/// The model has to be fully specified, inputs without update functions
+/// are written as `A, A`.
+pub fn write_rules(model: &BooleanNetwork, syntax: RuleSyntax)
+-> Result<String, String> {
+    let mut model_str = match syntax {
+        RuleSyntax::Bnet => String::from("targets, factors\n"),
+        RuleSyntax::Cana => String::new(),
+    };
+    for var_id in model.variables() {
+        let name = model.get_variable_name(var_id);
+        if !is_valid_name(name) {
+            return Err(format!("The variable name '{name}' is not supported \
+                in the rules."));
+        }
+        let function = model.get_update_function(var_id).clone()
+            .unwrap_or(FnUpdate::Var(var_id));
+        model_str.push_str(&format!("{name}{} {}\n",
+            syntax.assignment(), function_to_rule(&function, syntax, model)?));
+    }
+    Ok(model_str)
+}
+
+// Keywords and numbers are not names
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && keyword(name).is_none()
+    && !name.chars().all(|c| c.is_ascii_digit())
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => continue,
+            '&' => tokens.push(Token::And),
+            '|' => tokens.push(Token::Or),
+            '!' => tokens.push(Token::Not),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(keyword(&word).unwrap_or(Token::Name(word)));
+            },
+            c => return Err(format!("Unexpected character '{c}'.")),
+        }
+    }
+    Ok(tokens)
+}
+
+// The operators and constants written as words, in any case
+fn keyword(word: &str) -> Option<Token> {
+    match word.to_lowercase().as_str() {
+        "and" => Some(Token::And),
+        "or" => Some(Token::Or),
+        "not" => Some(Token::Not),
+        "0" | "false" => Some(Token::Const(false)),
+        "1" | "true" => Some(Token::Const(true)),
+        _ => None,
+    }
+}
+
+// The names are variables of `model`, see `read_rules`
+fn expression_to_function(
+    expression: &BooleanExpression,
+    model: &BooleanNetwork,
+) -> FnUpdate {
+    let function = |expression: &BooleanExpression|
+        expression_to_function(expression, model);
+    let binary = |op, left, right| FnUpdate::Binary(op,
+        Box::new(function(left)), Box::new(function(right)));
+    match expression {
+        BooleanExpression::Const(value) => FnUpdate::Const(*value),
+        BooleanExpression::Variable(name) =>
+            FnUpdate::Var(model.as_graph().find_variable(name).unwrap()),
+        BooleanExpression::Not(inner) => function(inner).negation(),
+        BooleanExpression::And(left, right) =>
+            binary(BinaryOp::And, left, right),
+        BooleanExpression::Or(left, right) =>
+            binary(BinaryOp::Or, left, right),
+        BooleanExpression::Xor(left, right) =>
+            binary(BinaryOp::Xor, left, right),
+        BooleanExpression::Imp(left, right) =>
+            binary(BinaryOp::Imp, left, right),
+        BooleanExpression::Iff(left, right) =>
+            binary(BinaryOp::Iff, left, right),
+    }
+}
+
+// Operators other than the conjunction and the disjunction are rewritten,
+// the rule formats do not support them
+fn function_to_rule(
+    function: &FnUpdate,
+    syntax: RuleSyntax,
+    model: &BooleanNetwork,
+) -> Result<String, String> {
+    let (and, or, not) = match syntax {
+        RuleSyntax::Bnet => (" & ", " | ", "!"),
+        RuleSyntax::Cana => (" and ", " or ", "not "),
+    };
+    // Operands of negations and of other binary operators are in parentheses
+    let operand = |function: &FnUpdate, parent: Option<BinaryOp>|
+    -> Result<String, String> {
+        let rule = function_to_rule(function, syntax, model)?;
+        Ok(match function {
+            FnUpdate::Binary(op, ..) if Some(*op) != parent =>
+                format!("({rule})"),
+            _ => rule,
+        })
+    };
+    let var = |var_id: &VariableId| model.get_variable_name(*var_id).clone();
+    Ok(match function {
+        FnUpdate::Const(value) => String::from(if *value { "1" } else { "0" }),
+        FnUpdate::Var(var_id) => var(var_id),
+        FnUpdate::Param(parameter, _) => return Err(format!(
+            "The model contains parameter '{}'.",
+            model[*parameter].get_name())),
+        FnUpdate::Not(inner) => format!("{not}{}", operand(inner, None)?),
+        FnUpdate::Binary(op, left, right) => {
+            let (left, right) = (left.as_ref().clone(), right.as_ref().clone());
+            match op {
+                BinaryOp::And => format!("{}{and}{}",
+                    operand(&left, Some(*op))?, operand(&right, Some(*op))?),
+                BinaryOp::Or => format!("{}{or}{}",
+                    operand(&left, Some(*op))?, operand(&right, Some(*op))?),
+                BinaryOp::Imp => function_to_rule(
+                    &left.negation().or(right), syntax, model)?,
+                BinaryOp::Iff => function_to_rule(
+                    &left.clone().and(right.clone())
+                        .or(left.negation().and(right.negation())),
+                    syntax, model)?,
+                BinaryOp::Xor => function_to_rule(
+                    &left.clone().and(right.clone().negation())
+                        .or(left.negation().and(right)),
+                    syntax, model)?,
+            }
+        },
+    })
+}
@@ -0,0 +1,292 @@
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, Monotonicity,
+    RegulatoryGraph, VariableId};
+use roxmltree::{Document, Node};
+
+
+/*******************************************************************************
+ * SBML-qual reader
+ * ================
+ * Elements are matched by their local names, so any version of the
+ * SBML-qual package is accepted. The output of a transition is the
+ * disjunction of its function terms with result level 1. With the default
+ * level 1, the output is 1 unless a function term with level 0 holds.
+ * Comparisons of a species with 0 or 1 in MathML become literals.
+ ******************************************************************************/
+
+struct Input {
+    species: String,
+    monotonicity: Option<Monotonicity>,
+    essential: Option<bool>,
+}
+
+struct Transition<'a> {
+    inputs: Vec<Input>,
+    output: String,
+    default_level: Option<bool>,
+    // The MathML of function terms with their result levels
+    terms: Vec<(bool, Node<'a, 'a>)>,
+}
+
+/// Reads a network from an SBML-qual document.
+///
+/// Transitions without function terms give unspecified update functions,
+/// uninterpreted functions (`csymbol`) give explicit parameters.
+pub fn read_sbml(model_str: &str) -> Result<BooleanNetwork, String> {
+    let document = Document::parse(model_str)
+        .map_err(|err| format!("Invalid XML, err: {err}"))?;
+    let sbml = document.root_element();
+    if sbml.tag_name().name() != "sbml" {
+        return Err("The root element is not <sbml>.".into());
+    }
+    let model = child(sbml, "model")
+        .ok_or("The document does not contain a model.")?;
+
+    let species = read_species(model)?;
+    let transitions = children(model, "listOfTransitions")
+        .map(read_transition)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut graph = RegulatoryGraph::new(species);
+    for transition in &transitions {
+        for input in &transition.inputs {
+            if graph.find_regulation(
+                    variable(&graph, &input.species)?,
+                    variable(&graph, &transition.output)?).is_some() {
+                continue;
+            }
+            // Without the attribute `essential`, an input is observable
+            // if the function uses it or is not given at all
+            let observable = input.essential.unwrap_or(
+                transition.terms.is_empty()
+                || transition.terms.iter()
+                    .any(|(_, math)| mentions(*math, &input.species)));
+            graph.add_regulation(&input.species, &transition.output,
+                observable, input.monotonicity)?;
+        }
+    }
+
+    let mut model = BooleanNetwork::new(graph);
+    for transition in &transitions {
+        let Some(default_level) = transition.default_level else {
+            continue;
+        };
+        let output = variable(model.as_graph(), &transition.output)?;
+        let mut terms = [None, None];
+        for (level, math) in &transition.terms {
+            let term = read_math(*math, &mut model)?;
+            let disjunction = &mut terms[usize::from(*level)];
+            *disjunction = Some(match disjunction.take() {
+                Some(disjunction) => FnUpdate::or(disjunction, term),
+                None => term,
+            });
+        }
+        let [zero_terms, one_terms] = terms;
+        let function = match (default_level, zero_terms, one_terms) {
+            (false, _, None) => FnUpdate::Const(false),
+            (false, _, Some(ones)) => ones,
+            (true, None, _) => FnUpdate::Const(true),
+            (true, Some(zeros), None) => zeros.negation(),
+            (true, Some(zeros), Some(ones)) => ones.or(zeros.negation()),
+        };
+        model.add_update_function(output, function)
+            .map_err(|err| format!("Invalid transition of '{}', err: {err}",
+                transition.output))?;
+    }
+    Ok(model)
+}
+
+fn read_species(model: Node) -> Result<Vec<String>, String> {
+    children(model, "listOfQualitativeSpecies")
+        .map(|species| {
+            let id = attribute(species, "id")
+                .ok_or("A qualitative species without id.")?;
+            match attribute(species, "maxLevel") {
+                None | Some("1") => Ok(id.to_string()),
+                Some(level) => Err(format!(
+                    "The species '{id}' is not Boolean (max level {level}).")),
+            }
+        })
+        .collect()
+}
+
+fn read_transition<'a>(transition: Node<'a, 'a>)
+-> Result<Transition<'a>, String> {
+    let inputs = children(transition, "listOfInputs")
+        .map(|input| Ok(Input {
+            species: attribute(input, "qualitativeSpecies")
+                .ok_or("An input without species.")?
+                .to_string(),
+            monotonicity: match attribute(input, "sign") {
+                Some("positive") => Some(Monotonicity::Activation),
+                Some("negative") => Some(Monotonicity::Inhibition),
+                _ => None,
+            },
+            essential: attribute(input, "essential").map(|value|
+                value == "true"),
+        }))
+        .collect::<Result<Vec<_>, String>>()?;
+    let outputs = children(transition, "listOfOutputs")
+        .map(|output| attribute(output, "qualitativeSpecies")
+            .ok_or("An output without species."))
+        .collect::<Result<Vec<_>, _>>()?;
+    let [output] = outputs.as_slice() else {
+        return Err("Only transitions with one output are supported.".into());
+    };
+
+    let mut default_level = None;
+    let mut terms = Vec::new();
+    for term in children(transition, "listOfFunctionTerms") {
+        let level = match attribute(term, "resultLevel") {
+            Some("0") => false,
+            Some("1") => true,
+            level => return Err(format!(
+                "Invalid result level '{}' of '{output}'.",
+                level.unwrap_or_default())),
+        };
+        match term.tag_name().name() {
+            "defaultTerm" => default_level = Some(level),
+            _ => terms.push((level, child(term, "math")
+                .and_then(|math| math.first_element_child())
+                .ok_or(format!("A function term of '{output}' \
+                    without math."))?)),
+        }
+    }
+    if !terms.is_empty() && default_level.is_none() {
+        return Err(format!("The transition of '{output}' has no default \
+            term."));
+    }
+    Ok(Transition { inputs, output: output.to_string(), default_level, terms })
+}
+
+fn read_math(math: Node, model: &mut BooleanNetwork)
+-> Result<FnUpdate, String> {
+    match math.tag_name().name() {
+        "true" => Ok(FnUpdate::Const(true)),
+        "false" => Ok(FnUpdate::Const(false)),
+        "ci" => Ok(FnUpdate::Var(variable(model.as_graph(), text(math))?)),
+        "cn" => Ok(FnUpdate::Const(read_level(math)?)),
+        "apply" => {
+            let mut elements = math.children().filter(Node::is_element);
+            let operator = elements.next().ok_or("An empty <apply>.")?;
+            let args = elements.collect::<Vec<_>>();
+            match operator.tag_name().name() {
+                "csymbol" => read_parameter(text(operator), &args, model),
+                "eq" | "neq" | "lt" | "leq" | "gt" | "geq" =>
+                    read_comparison(operator.tag_name().name(), &args, model),
+                name => {
+                    let mut args = args.iter()
+                        .map(|arg| read_math(*arg, model))
+                        .collect::<Result<Vec<_>, String>>()?
+                        .into_iter();
+                    let first = args.next()
+                        .ok_or(format!("<{name}> without arguments."))?;
+                    match name {
+                        "not" => Ok(first.negation()),
+                        "and" => Ok(args.fold(first, FnUpdate::and)),
+                        "or" => Ok(args.fold(first, FnUpdate::or)),
+                        "xor" => Ok(args.fold(first, FnUpdate::xor)),
+                        "implies" => args.next()
+                            .map(|second| first.implies(second))
+                            .ok_or("<implies> with one argument.".into()),
+                        _ => Err(format!("Unsupported MathML operator \
+                            <{name}>.")),
+                    }
+                },
+            }
+        },
+        name => Err(format!("Unsupported MathML element <{name}>.")),
+    }
+}
+
+// A comparison of a species with a level is a literal of the species
+fn read_comparison(operator: &str, args: &[Node], model: &BooleanNetwork)
+-> Result<FnUpdate, String> {
+    let invalid = || format!("Unsupported comparison <{operator}>, \
+        expected a species and a level.");
+    let (species, level, operator) = match args {
+        [species, level] if species.has_tag_name("ci") =>
+            (*species, *level, operator),
+        // Swap the sides
+        [level, species] if species.has_tag_name("ci") =>
+            (*species, *level, match operator {
+                "lt" => "gt",
+                "leq" => "geq",
+                "gt" => "lt",
+                "geq" => "leq",
+                operator => operator,
+            }),
+        _ => return Err(invalid()),
+    };
+    if !level.has_tag_name("cn") {
+        return Err(invalid());
+    }
+    let var = FnUpdate::Var(variable(model.as_graph(), text(species))?);
+    Ok(match (operator, read_level(level)?) {
+        ("eq", true) | ("neq", false) | ("geq", true) | ("gt", false) => var,
+        ("eq", false) | ("neq", true) | ("leq", false) | ("lt", true) =>
+            var.negation(),
+        ("geq", false) | ("leq", true) => FnUpdate::Const(true),
+        _ => FnUpdate::Const(false),
+    })
+}
+
+fn read_parameter(name: &str, args: &[Node], model: &mut BooleanNetwork)
+-> Result<FnUpdate, String> {
+    let args = args.iter()
+        .map(|arg| match arg.tag_name().name() {
+            "ci" => variable(model.as_graph(), text(*arg)),
+            _ => Err(format!("Arguments of '{name}' must be species.")),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let parameter = match model.find_parameter(name) {
+        Some(parameter) => parameter,
+        None => model.add_parameter(name, args.len() as u32)?,
+    };
+    if model[parameter].get_arity() as usize != args.len() {
+        return Err(format!("The parameter '{name}' is used with different \
+            arities."));
+    }
+    Ok(FnUpdate::Param(parameter, args))
+}
+
+fn variable(graph: &RegulatoryGraph, name: &str) -> Result<VariableId, String> {
+    graph.find_variable(name)
+        .ok_or(format!("Unknown qualitative species '{name}'."))
+}
+
+fn read_level(cn: Node) -> Result<bool, String> {
+    match text(cn) {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        level => Err(format!("Invalid level '{level}', expected 0 or 1.")),
+    }
+}
+
+fn mentions(math: Node, species: &str) -> bool {
+    math.descendants()
+        .any(|node| node.has_tag_name("ci") && text(node) == species)
+}
+
+fn text<'a>(node: Node<'a, '_>) -> &'a str {
+    node.text().unwrap_or_default().trim()
+}
+
+// Attributes are usually in the namespace of SBML-qual
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().iter()
+        .find(|attribute| attribute.name() == name)
+        .map(|attribute| attribute.value())
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str)
+-> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+// The elements of the list `name` in `node`
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str)
+-> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    child(node, name)
+        .into_iter()
+        .flat_map(|list| list.children().filter(Node::is_element))
+}
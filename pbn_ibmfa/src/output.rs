@@ -0,0 +1,196 @@
+use std::fmt::Write;
+
+
+/*******************************************************************************
+ * Tables
+ * ======
+ * The outputs are JSON by default, the tables of CSV or TSV are meant
+ * for spreadsheets and plotting tools. Parameter names contain commas,
+ * so the cells of CSV are quoted as in RFC 4180 when needed.
+ ******************************************************************************/
+
+/// A table of strings with a header row.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: Vec<String>) -> Table {
+        Table { header, rows: Vec::new() }
+    }
+
+    /// Comma-separated values, the cells with commas, quotes or line breaks
+    /// are quoted.
+    pub fn to_csv(&self) -> String {
+        self.join(',', |cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+    }
+
+    /// Tab-separated values, tabs and line breaks in cells are replaced
+    /// by spaces.
+    pub fn to_tsv(&self) -> String {
+        self.join('\t', |cell| cell.replace(['\t', '\n', '\r'], " "))
+    }
+
+    fn join(&self, separator: char, escape: impl Fn(&str) -> String)
+    -> String {
+        std::iter::once(&self.header).chain(&self.rows)
+            .map(|row| row.iter()
+                .map(|cell| escape(cell))
+                .collect::<Vec<_>>()
+                .join(&separator.to_string()) + "\n")
+            .collect()
+    }
+}
+
+/// The lines as a comment of XML (e.g. SVG or SBML). The double hyphens
+/// not allowed in comments are escaped as in JSON strings.
+pub fn xml_comment(lines: &[String]) -> String {
+    format!("<!--\n{}\n-->", lines.join("\n").replace("--", "-\\u002d"))
+}
+
+
+/*******************************************************************************
+ * Plots
+ * =====
+ * A line plot of probabilities in time written as SVG, the same as drawn
+ * by `scripts/plot_simulations.py`: a line per variable with optional
+ * bands of the spread across colors, the range and the standard deviation.
+ ******************************************************************************/
+
+/// The probabilities of a variable after every step (the initial first).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlotLine {
+    pub name: String,
+    pub values: Vec<f32>,
+    /// The minimal and maximal probabilities, drawn as a light band.
+    pub envelope: Option<(Vec<f32>, Vec<f32>)>,
+    /// The standard deviation, drawn as a band around the values.
+    pub std: Option<Vec<f32>>,
+}
+
+// The default colors of matplotlib
+const COLORS: [&str; 10] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728",
+    "#9467bd", "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf"];
+const WIDTH: f32 = 640.0;
+const HEIGHT: f32 = 480.0;
+const MARGIN: (f32, f32, f32, f32) = (20.0, 20.0, 60.0, 70.0);
+const Y_RANGE: (f32, f32) = (-0.05, 1.05);
+
+/// Draws `lines` of probabilities in time as an SVG document.
+pub fn line_plot_svg(lines: &[PlotLine]) -> String {
+    let (top, right, bottom, left) = MARGIN;
+    let steps = lines.iter()
+        .map(|line| line.values.len())
+        .max()
+        .unwrap_or(0)
+        .max(2) - 1;
+    let x = |step: usize| left
+        + step as f32 / steps as f32 * (WIDTH - left - right);
+    let y = |prob: f32| top + (Y_RANGE.1 - prob) / (Y_RANGE.1 - Y_RANGE.0)
+        * (HEIGHT - top - bottom);
+    let points = |values: &mut dyn Iterator<Item = (usize, f32)>| values
+        .map(|(step, prob)| format!("{:.2},{:.2}", x(step), y(prob)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // The upper bound forwards, the lower one backwards
+    let band = |lower: &[f32], upper: &[f32]| points(
+        &mut upper.iter().copied().enumerate()
+            .chain(lower.iter().copied().enumerate().rev()));
+
+    let mut svg = String::new();
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+        width=\"{WIDTH}\" height=\"{HEIGHT}\" \
+        viewBox=\"0 0 {WIDTH} {HEIGHT}\" font-family=\"sans-serif\">")
+        .unwrap();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")
+        .unwrap();
+
+    // Axes with ticks
+    writeln!(svg, "<rect x=\"{left}\" y=\"{top}\" width=\"{}\" \
+        height=\"{}\" fill=\"none\" stroke=\"black\"/>",
+        WIDTH - left - right, HEIGHT - top - bottom).unwrap();
+    let tick_step = tick_step(steps);
+    for step in (0..=steps).step_by(tick_step) {
+        writeln!(svg, "<line x1=\"{0:.2}\" y1=\"{1}\" x2=\"{0:.2}\" \
+            y2=\"{2}\" stroke=\"black\"/><text x=\"{0:.2}\" y=\"{3}\" \
+            font-size=\"12\" text-anchor=\"middle\">{step}</text>",
+            x(step), HEIGHT - bottom, HEIGHT - bottom + 5.0,
+            HEIGHT - bottom + 20.0).unwrap();
+    }
+    for tick in 0..=5 {
+        let prob = tick as f32 / 5.0;
+        writeln!(svg, "<line x1=\"{0}\" y1=\"{1:.2}\" x2=\"{2}\" \
+            y2=\"{1:.2}\" stroke=\"black\"/><text x=\"{3}\" y=\"{4:.2}\" \
+            font-size=\"12\" text-anchor=\"end\">{prob:.1}</text>",
+            left - 5.0, y(prob), left, left - 8.0, y(prob) + 4.0).unwrap();
+    }
+    writeln!(svg, "<text x=\"{:.2}\" y=\"{}\" font-size=\"14\" \
+        text-anchor=\"middle\">time</text>",
+        (left + WIDTH - right) / 2.0, HEIGHT - 20.0).unwrap();
+    writeln!(svg, "<text transform=\"translate(20 {:.2}) rotate(-90)\" \
+        font-size=\"14\" text-anchor=\"middle\">probability</text>",
+        (top + HEIGHT - bottom) / 2.0).unwrap();
+
+    for (index, line) in lines.iter().enumerate() {
+        let color = COLORS[index % COLORS.len()];
+        if let Some((min, max)) = &line.envelope {
+            writeln!(svg, "<polygon points=\"{}\" fill=\"{color}\" \
+                fill-opacity=\"0.1\"/>", band(min, max)).unwrap();
+        }
+        if let Some(std) = &line.std {
+            let lower = line.values.iter().zip(std)
+                .map(|(value, std)| value - std)
+                .collect::<Vec<_>>();
+            let upper = line.values.iter().zip(std)
+                .map(|(value, std)| value + std)
+                .collect::<Vec<_>>();
+            writeln!(svg, "<polygon points=\"{}\" fill=\"{color}\" \
+                fill-opacity=\"0.2\"/>", band(&lower, &upper)).unwrap();
+        }
+        writeln!(svg, "<polyline points=\"{}\" fill=\"none\" \
+            stroke=\"{color}\" stroke-width=\"1.5\"/>",
+            points(&mut line.values.iter().copied().enumerate())).unwrap();
+    }
+
+    // Legend in the upper right corner
+    for (index, line) in lines.iter().enumerate() {
+        let color = COLORS[index % COLORS.len()];
+        let legend_y = top + 20.0 + 18.0 * index as f32;
+        writeln!(svg, "<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" \
+            stroke=\"{color}\" stroke-width=\"1.5\"/><text x=\"{3}\" \
+            y=\"{4}\" font-size=\"12\">{5}</text>",
+            WIDTH - right - 100.0, legend_y, WIDTH - right - 80.0,
+            WIDTH - right - 75.0, legend_y + 4.0, escape_xml(&line.name))
+            .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// At most about ten ticks, 1, 2 or 5 times a power of ten steps apart
+fn tick_step(steps: usize) -> usize {
+    let mut power = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if steps / (factor * power) <= 10 {
+                return factor * power;
+            }
+        }
+        power *= 10;
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,351 @@
+use biodivine_lib_bdd::{Bdd, BddVariable,
+    boolean_expression::BooleanExpression};
+use biodivine_lib_param_bn::{ParameterId, VariableId};
+
+use crate::expression::{Token, parse_tokens};
+use crate::symbolic_sync_graph::SymbSyncGraph;
+
+
+/*******************************************************************************
+ * Parameter names
+ * ===============
+ * The bdd variables of parameters are the rows of function tables. They
+ * are named by lib-param-bn as `f_a[0,1]` for the implicit update function
+ * of `a`, and `p[0,1]` for the explicit parameter `p` of arity 2. Readable
+ * names show the values of the regulators of implicit functions,
+ * `a(0b,1c)`, and the arguments of explicit parameters, `p(0,1)`, or just
+ * `p` for arity 0. Both forms are parsed, also in constraints on colors.
+ ******************************************************************************/
+
+/// The function table a parameter variable belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterTable {
+    /// The implicit update function of the variable.
+    Implicit(VariableId),
+    /// The explicit parameter.
+    Explicit(ParameterId),
+}
+
+/// Returns the function table of `bdd_var` with the row, the values
+/// of arguments. `None` if `bdd_var` is not a parameter.
+pub fn parameter_row(bdd_var: BddVariable, sync_graph: &SymbSyncGraph)
+-> Option<(ParameterTable, Vec<bool>)> {
+    let network = sync_graph.as_network();
+    let context = sync_graph.symbolic_context();
+    let implicit = network.variables()
+        .filter(|var_id| network.get_update_function(*var_id).is_none())
+        .flat_map(|var_id| context.get_implicit_function_table(var_id)
+            .into_iter()
+            .map(move |(row, row_var)|
+                (ParameterTable::Implicit(var_id), row, row_var)));
+    let explicit = network.parameters()
+        .flat_map(|parameter| context.get_explicit_function_table(parameter)
+            .into_iter()
+            .map(move |(row, row_var)|
+                (ParameterTable::Explicit(parameter), row, row_var)));
+    implicit.chain(explicit)
+        .find(|(_, _, row_var)| *row_var == bdd_var)
+        .map(|(table, row, _)| (table, row))
+}
+
+/// Returns the readable name of the parameter `bdd_var`. Names of other
+/// bdd variables are returned unchanged.
+pub fn parameter_name(bdd_var: BddVariable, sync_graph: &SymbSyncGraph)
+-> String {
+    let network = sync_graph.as_network();
+    let bit = |value: &bool| if *value { '1' } else { '0' };
+    match parameter_row(bdd_var, sync_graph) {
+        Some((ParameterTable::Implicit(var_id), row)) => format!("{}({})",
+            network.get_variable_name(var_id),
+            network.regulators(var_id).iter().zip(&row)
+                .map(|(regulator, value)| format!("{}{}",
+                    bit(value), network.get_variable_name(*regulator)))
+                .collect::<Vec<_>>()
+                .join(",")),
+        Some((ParameterTable::Explicit(parameter), row)) if row.is_empty() =>
+            network[parameter].get_name().clone(),
+        Some((ParameterTable::Explicit(parameter), row)) => format!("{}({})",
+            network[parameter].get_name(),
+            row.iter().map(|value| bit(value).to_string())
+                .collect::<Vec<_>>()
+                .join(",")),
+        None => sync_graph.symbolic_context().bdd_variable_set()
+            .name_of(bdd_var),
+    }
+}
+
+/// Finds the parameter of the given readable or bdd variable name.
+pub fn parse_parameter(name: &str, sync_graph: &SymbSyncGraph)
+-> Result<BddVariable, String> {
+    let context = sync_graph.symbolic_context();
+    let unknown = || format!("The model does not contain parameter '{name}'.");
+    if let Some(bdd_var) = context.bdd_variable_set().var_by_name(name)
+            .filter(|bdd_var| context.parameter_variables().contains(bdd_var)) {
+        return Ok(bdd_var);
+    }
+
+    let network = sync_graph.as_network();
+    let (head, args) = match name.split_once('(') {
+        Some((head, args)) => (head, args.strip_suffix(')')
+            .ok_or(format!("Invalid parameter '{name}', missing ')'."))?),
+        None => (name, ""),
+    };
+    let args = args.split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>();
+
+    let (table, row) = if let Some(parameter) = network.find_parameter(head) {
+        let row = args.iter()
+            .map(|arg| match *arg {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("Invalid parameter '{name}'. Expected \
+                    arguments '0'/'1', found '{arg}'.")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (context.get_explicit_function_table(parameter), row)
+    } else {
+        let var_id = network.as_graph().find_variable(head)
+            .filter(|var_id| network.get_update_function(*var_id).is_none())
+            .ok_or_else(unknown)?;
+        let regulators = network.regulators(var_id);
+        if regulators.len() != args.len() {
+            return Err(format!("Invalid parameter '{name}'. Expected {} \
+                regulators of '{head}'.", regulators.len()));
+        }
+        let row = regulators.iter().zip(&args)
+            .map(|(regulator, arg)| {
+                let regulator = network.get_variable_name(*regulator);
+                match split_bit(arg) {
+                    Some((value, rest)) if rest == regulator => Ok(value),
+                    Some(_) => Err(format!("Invalid parameter '{name}'. \
+                        Expected regulator '{regulator}', found '{arg}'.")),
+                    None => Err(format!("Invalid parameter '{name}'. \
+                        Expected arguments starting with '0'/'1', found \
+                        '{arg}'.")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (context.get_implicit_function_table(var_id), row)
+    };
+    table.into_iter()
+        .find(|(table_row, _)| *table_row == row)
+        .map(|(_, bdd_var)| bdd_var)
+        .ok_or_else(unknown)
+}
+
+/// Splits the value off an argument of a parameter, the rest is the name
+/// of the regulator.
+fn split_bit(arg: &str) -> Option<(bool, &str)> {
+    arg.strip_prefix('0').map(|rest| (false, rest))
+        .or_else(|| arg.strip_prefix('1').map(|rest| (true, rest)))
+}
+
+/// Parses parameter fixes. Syntax: "{name}={value}", the name is readable
+/// or the name of the bdd variable, the value is "0" or "1".
+pub fn parse_parameter_fixes(fixes: &[String], sync_graph: &SymbSyncGraph)
+-> Result<Vec<(BddVariable, bool)>, String> {
+    fixes.iter()
+        .map(|fix| {
+            let (name, value) = fix.rsplit_once('=')
+                .ok_or(format!("Invalid parameter '{fix}'. \
+                    Expected format '{{name}}={{value}}'."))?;
+            let value = match value.trim() {
+                "0" => false,
+                "1" => true,
+                _ => return Err(format!("Invalid parameter '{fix}'. \
+                    Expected value '0'/'1', found '{value}'.")),
+            };
+            Ok((parse_parameter(name.trim(), sync_graph)?, value))
+        })
+        .collect()
+}
+
+/// Parses a constraint on colors, a Boolean expression over parameters.
+///
+/// Parameters are given by their readable or bdd variable names, the
+/// operators are `!`, `&`, `^`, `|`, `=>` and `<=>` (from the strongest),
+/// the constants `true` and `false`.
+pub fn parse_constraint(constraint: &str, sync_graph: &SymbSyncGraph)
+-> Result<Bdd, String> {
+    let invalid = |err| format!("Invalid constraint '{constraint}'. {err}");
+    tokenize(constraint)
+        .and_then(|tokens| parse_tokens(&tokens))
+        .and_then(|expression| expression_to_bdd(&expression, sync_graph))
+        .map_err(invalid)
+}
+
+// Names include the arguments, `a(0b,1c)` or `f_a[0,1]`
+fn tokenize(constraint: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = constraint.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => continue,
+            '!' => tokens.push(Token::Not),
+            '&' => tokens.push(Token::And),
+            '|' => tokens.push(Token::Or),
+            '^' => tokens.push(Token::Xor),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '=' if chars.next_if_eq(&'>').is_some() => tokens.push(Token::Imp),
+            '<' if chars.next_if_eq(&'=').is_some()
+                    && chars.next_if_eq(&'>').is_some() =>
+                tokens.push(Token::Iff),
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut name = String::from(c);
+                while let Some(c) = chars.next_if(|c|
+                        c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if let Some(open) = chars.next_if(|c| *c == '(' || *c == '[') {
+                    let close = if open == '(' { ')' } else { ']' };
+                    name.push(open);
+                    loop {
+                        let c = chars.next()
+                            .ok_or(format!("Missing '{close}' in '{name}'."))?;
+                        name.push(c);
+                        if c == close {
+                            break;
+                        }
+                    }
+                }
+                tokens.push(match name.as_str() {
+                    "true" => Token::Const(true),
+                    "false" => Token::Const(false),
+                    _ => Token::Name(name),
+                });
+            },
+            c => return Err(format!("Unexpected character '{c}'.")),
+        }
+    }
+    Ok(tokens)
+}
+
+// The names are parameters, see `parse_parameter`
+fn expression_to_bdd(
+    expression: &BooleanExpression,
+    sync_graph: &SymbSyncGraph,
+) -> Result<Bdd, String> {
+    let bdd = |expression: &BooleanExpression|
+        expression_to_bdd(expression, sync_graph);
+    Ok(match expression {
+        BooleanExpression::Const(value) =>
+            sync_graph.symbolic_context().mk_constant(*value),
+        BooleanExpression::Variable(name) => sync_graph.symbolic_context()
+            .bdd_variable_set()
+            .mk_var(parse_parameter(name, sync_graph)?),
+        BooleanExpression::Not(inner) => bdd(inner)?.not(),
+        BooleanExpression::And(left, right) => bdd(left)?.and(&bdd(right)?),
+        BooleanExpression::Or(left, right) => bdd(left)?.or(&bdd(right)?),
+        BooleanExpression::Xor(left, right) => bdd(left)?.xor(&bdd(right)?),
+        BooleanExpression::Imp(left, right) => bdd(left)?.imp(&bdd(right)?),
+        BooleanExpression::Iff(left, right) => bdd(left)?.iff(&bdd(right)?),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    use crate::model_format::read_model_file;
+    use crate::symbolic_sync_graph::SymbSyncGraph;
+    use super::{parameter_name, parse_constraint, parse_parameter,
+        parse_parameter_fixes};
+
+    fn model(name: &str) -> SymbSyncGraph {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../models")
+            .join(name);
+        SymbSyncGraph::new(read_model_file(&path).unwrap())
+    }
+
+    fn assert_names_parsed(sync_graph: &SymbSyncGraph) {
+        let bdd_var_set = sync_graph.symbolic_context().bdd_variable_set();
+        for bdd_var in sync_graph.symbolic_context().parameter_variables() {
+            let name = parameter_name(*bdd_var, sync_graph);
+            assert_eq!(parse_parameter(&name, sync_graph), Ok(*bdd_var));
+            let name = bdd_var_set.name_of(*bdd_var);
+            assert_eq!(parse_parameter(&name, sync_graph), Ok(*bdd_var));
+        }
+    }
+
+    #[test]
+    fn implicit_parameter_names() {
+        let sync_graph = model("MIR-9-NEUROGENESIS_fpared.aeon");
+        assert!(!sync_graph.symbolic_context().parameter_variables()
+            .is_empty());
+        assert_names_parsed(&sync_graph);
+    }
+
+    #[test]
+    fn explicit_parameter_names() {
+        let network = BooleanNetwork::try_from(
+            "a -> b\nb -> a\n$a: q & b\n$b: p(a)").unwrap();
+        let sync_graph = SymbSyncGraph::new(network);
+        let bdd_var = sync_graph.symbolic_context().parameter_variables()[0];
+        assert_eq!(parameter_name(bdd_var, &sync_graph), "p(0)");
+        assert_names_parsed(&sync_graph);
+    }
+
+    #[test]
+    fn constraint_precedence() {
+        let sync_graph = model("example_fpared.aeon");
+        let [p, q, r] = ["b(0b,0c)", "b(0b,1c)", "b(1b,0c)"];
+        for name in [p, q, r] {
+            assert!(parse_constraint(name, &sync_graph).is_ok());
+        }
+        let same = [
+            (format!("!{p} & {q}"), format!("(!{p}) & {q}")),
+            (format!("{p} | {q} & {r}"), format!("{p} | ({q} & {r})")),
+            (format!("{p} ^ {q} & {r}"), format!("{p} ^ ({q} & {r})")),
+            (format!("{p} | {q} ^ {r}"), format!("{p} | ({q} ^ {r})")),
+            (format!("{p} => {q} | {r}"), format!("{p} => ({q} | {r})")),
+            (format!("{p} => {q} => {r}"), format!("{p} => ({q} => {r})")),
+            (format!("{p} <=> {q} => {r}"), format!("{p} <=> ({q} => {r})")),
+            (format!("{p} ^ {q} ^ {r}"), format!("({p} ^ {q}) ^ {r}")),
+            (format!("{p} & true"), p.to_string()),
+        ];
+        for (constraint, expected) in same {
+            assert_eq!(parse_constraint(&constraint, &sync_graph),
+                parse_constraint(&expected, &sync_graph), "{constraint}");
+        }
+        assert_ne!(parse_constraint(&format!("{p} | {q} & {r}"), &sync_graph),
+            parse_constraint(&format!("({p} | {q}) & {r}"), &sync_graph));
+        assert_ne!(parse_constraint(&format!("{p} => {q} => {r}"), &sync_graph),
+            parse_constraint(&format!("({p} => {q}) => {r}"), &sync_graph));
+    }
+
+    #[test]
+    fn malformed_constraints() {
+        let sync_graph = model("example_fpared.aeon");
+        let constraints = [
+            "", "(", ")", "!", "b(0b,0c) &", "b(0b,0c) b(0b,1c)",
+            "(b(0b,0c)", "b(0b,0c))", "b(0b,0c) = b(0b,1c)", "b(0b,0c",
+            "b(0b)", "b(0b,0c,1a)", "b(2b,0c)", "b(0c,0b)", "b(b,c)", "b()",
+            "x(0b,0c)", "a", "f_b[0,1", "f_b[0,0,0]", "b(0b,0c) # 1", "é",
+        ];
+        for constraint in constraints {
+            assert!(parse_constraint(constraint, &sync_graph).is_err(),
+                "{constraint}");
+        }
+    }
+    #[test]
+    fn parameter_fixes() {
+        let sync_graph = model("example_fpared.aeon");
+        let fixes = ["b(0b,0c)=1".to_string(), " f_b[0,1] = 0 ".to_string()];
+        let fixes = parse_parameter_fixes(&fixes, &sync_graph).unwrap();
+        assert_eq!(fixes[0], (parse_parameter("b(0b,0c)", &sync_graph)
+            .unwrap(), true));
+        assert_eq!(fixes[1], (parse_parameter("f_b[0,1]", &sync_graph)
+            .unwrap(), false));
+        for fix in ["b(0b,0c)", "b(0b,0c)=2", "b(0b,0c)=", "=1", "x=1"] {
+            assert!(parse_parameter_fixes(&[fix.to_string()], &sync_graph)
+                .is_err(), "{fix}");
+        }
+    }
+}
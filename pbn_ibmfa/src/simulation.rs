@@ -0,0 +1,359 @@
+use biodivine_lib_bdd::BddValuation;
+use biodivine_lib_param_bn::VariableId;
+use biodivine_lib_param_bn::symbolic_async_graph::GraphColors;
+
+use crate::driver_set::fixes::DriverSet;
+use crate::symbolic_sync_graph::SymbSyncGraph;
+
+
+/*******************************************************************************
+ * Exact simulation
+ * ================
+ * The reference for IBMFA. A color is a fully specified network, so every
+ * initial state has a single trajectory in the synchronous semantics. The
+ * probability of a variable after a step is the probability of the initial
+ * states (uniformly distributed by default) whose trajectories have
+ * the variable true after the step. Colors are equally likely, so the average
+ * dynamics of the model is the mean of the trajectories of colors. Their
+ * spread shows whether the colors agree.
+ ******************************************************************************/
+
+/// Probabilities of variables (in the order of the network) after every step.
+pub type Trajectory = Vec<Vec<f32>>;
+
+/// A distribution of initial states.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InitialStates {
+    /// Independent probabilities of variables being true (in the order
+    /// of the network).
+    Probabilities(Vec<f32>),
+    /// Equally likely states, the values of variables in the order
+    /// of the network.
+    States(Vec<Vec<bool>>),
+}
+
+impl InitialStates {
+    /// All the states of `num_vars` variables equally likely.
+    pub fn uniform(num_vars: usize) -> InitialStates {
+        InitialStates::Probabilities(vec![0.5; num_vars])
+    }
+
+    /// Probabilities of variables being true, the input of IBMFA.
+    ///
+    /// * `fixes` - Fixed variables, they override the distribution.
+    pub fn probabilities(&self, fixes: &DriverSet) -> Vec<f32> {
+        let mut probs = match self {
+            InitialStates::Probabilities(probs) => probs.clone(),
+            InitialStates::States(states) => (0..states[0].len())
+                .map(|var| states.iter()
+                    .filter(|state| state[var])
+                    .count() as f32 / states.len() as f32)
+                .collect(),
+        };
+        for (var_id, value) in fixes {
+            probs[var_id.to_index()] = if *value { 1.0 } else { 0.0 };
+        }
+        probs
+    }
+
+    /// Visits every possible state with its probability.
+    ///
+    /// Returns an error if there are too many states to enumerate,
+    /// i.e. 64 or more variables are neither true nor false surely.
+    ///
+    /// * `fixes` - Fixed variables, they override the distribution.
+    pub fn for_each_state(
+        &self,
+        fixes: &DriverSet,
+        mut visit: impl FnMut(&[bool], f64),
+    ) -> Result<(), String> {
+        let fix = |state: &mut Vec<bool>| for (var_id, value) in fixes {
+            state[var_id.to_index()] = *value;
+        };
+        match self {
+            InitialStates::Probabilities(_) => {
+                let probs = self.probabilities(fixes);
+                // Only the uncertain variables are enumerated
+                let free = (0..probs.len())
+                    .filter(|var| probs[*var] > 0.0 && probs[*var] < 1.0)
+                    .collect::<Vec<_>>();
+                if free.len() >= u64::BITS as usize {
+                    return Err(format!("Brute force is infeasible for {} \
+                        variables with uncertain initial values.", free.len()));
+                }
+                let mut state = probs.iter()
+                    .map(|prob| *prob >= 1.0)
+                    .collect::<Vec<_>>();
+                for bits in 0..1_u64 << free.len() {
+                    let mut weight = 1.0;
+                    for (bit, var) in free.iter().enumerate() {
+                        state[*var] = bits >> bit & 1 == 1;
+                        weight *= if state[*var] { probs[*var] as f64 }
+                            else { 1.0 - probs[*var] as f64 };
+                    }
+                    visit(&state, weight);
+                }
+            },
+            InitialStates::States(states) => for state in states {
+                let mut state = state.clone();
+                fix(&mut state);
+                visit(&state, 1.0 / states.len() as f64);
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Returns the exact trajectory of `color` by simulating every initial state,
+/// an error if they cannot be enumerated, see `for_each_state`.
+///
+/// * `color` - A valuation of parameter variables, see `nth_color`.
+/// * `driver_set` - Fixed variables. Fixed also in the initial states unless
+///     released immediately.
+/// * `pulse` - Release the driver-set after the given number of steps.
+/// * `time_steps` - Length of the simulation.
+/// * `initial` - The distribution of initial states.
+pub fn color_trajectory(
+    sync_graph: &SymbSyncGraph,
+    color: &BddValuation,
+    driver_set: &DriverSet,
+    pulse: Option<usize>,
+    time_steps: usize,
+    initial: &InitialStates,
+) -> Result<Trajectory, String> {
+    let context = sync_graph.symbolic_context();
+    let variables = sync_graph.as_network().variables().collect::<Vec<_>>();
+    let functions = sync_graph.get_pupdate_functions().iter()
+        .map(|pupdate_function| pupdate_function.restricted(color))
+        .collect::<Vec<_>>();
+    let fixed_at = |step: usize| pulse.is_none_or(|pulse| step < pulse);
+    let initial_fixes = if fixed_at(0) { driver_set.clone() }
+        else { DriverSet::new() };
+
+    // The probabilities are the weighted counts of trajectories
+    let mut probs = vec![vec![0.0_f64; variables.len()]; time_steps];
+    let mut valuation =
+        BddValuation::all_false(context.bdd_variable_set().num_vars());
+    initial.for_each_state(&initial_fixes, |initial, weight| {
+        let mut state = initial.to_vec();
+        for (step, step_probs) in probs.iter_mut().enumerate() {
+            for (bdd_var, value) in context.state_variables().iter()
+                    .zip(&state) {
+                valuation.set_value(*bdd_var, *value);
+            }
+            state = variables.iter().zip(&functions)
+                .map(|(var_id, function)| match driver_set.get(var_id) {
+                    Some(value) if fixed_at(step) => *value,
+                    _ => function.eval_in(&valuation),
+                })
+                .collect();
+            for (prob, value) in step_probs.iter_mut().zip(&state) {
+                if *value {
+                    *prob += weight;
+                }
+            }
+        }
+    })?;
+
+    Ok(probs.into_iter()
+        .map(|step_probs| step_probs.into_iter()
+            .map(|prob| prob as f32)
+            .collect())
+        .collect())
+}
+
+/// Visits the groups of `colors` with the same update functions
+/// of `variables`, the trajectories of colors in a group are the same
+/// if the functions of the other variables do not matter (e.g. they are
+/// fixed).
+///
+/// Grouped are only the colors of parametrized variables, the groups are
+/// visited in the order of the admissible functions, see `function_classes`.
+pub fn for_each_group(
+    sync_graph: &SymbSyncGraph,
+    colors: &GraphColors,
+    variables: &[VariableId],
+    visit: &mut impl FnMut(&GraphColors),
+) {
+    let parametrized = |var_id: &&VariableId| !sync_graph
+        .get_pupdate_functions()[var_id.to_index()]
+        .get_parameters().is_empty();
+    let mut variables = variables.iter().filter(parametrized);
+    match variables.next() {
+        None => visit(colors),
+        Some(var_id) => {
+            let rest = variables.copied().collect::<Vec<_>>();
+            for (_, class) in sync_graph.function_classes(*var_id, colors) {
+                for_each_group(sync_graph, &class, &rest, visit);
+            }
+        },
+    }
+}
+
+/// The distribution of trajectories across colors, accumulated one by one.
+/// Every trajectory has a weight, e.g. the number of colors sharing it.
+///
+/// The probabilities of each variable after each step are summarized
+/// by the mean, the standard deviation, the minimum, the maximum and
+/// a histogram of weights over `bins` intervals of equal width in [0, 1].
+#[derive(Clone, Debug)]
+pub struct TrajectoryStats {
+    bins: usize,
+    weight: f64,
+    sum: Vec<Vec<f64>>,
+    sum_squares: Vec<Vec<f64>>,
+    min: Trajectory,
+    max: Trajectory,
+    histogram: Vec<Vec<Vec<f64>>>,
+}
+
+impl TrajectoryStats {
+    pub fn new(bins: usize) -> TrajectoryStats {
+        TrajectoryStats {
+            bins: bins.max(1),
+            weight: 0.0,
+            sum: Vec::new(),
+            sum_squares: Vec::new(),
+            min: Vec::new(),
+            max: Vec::new(),
+            histogram: Vec::new(),
+        }
+    }
+
+    /// Adds a trajectory with its `weight`. All the trajectories must have
+    /// the same length.
+    pub fn add(&mut self, trajectory: &Trajectory, weight: f64) {
+        if self.sum.is_empty() {
+            let zeros = trajectory.iter()
+                .map(|probs| vec![0.0; probs.len()])
+                .collect::<Vec<_>>();
+            self.sum = zeros.clone();
+            self.sum_squares = zeros;
+            self.min = trajectory.clone();
+            self.max = trajectory.clone();
+            self.histogram = trajectory.iter()
+                .map(|probs| vec![vec![0.0; self.bins]; probs.len()])
+                .collect();
+        }
+        self.weight += weight;
+        for (step, probs) in trajectory.iter().enumerate() {
+            for (var, &prob) in probs.iter().enumerate() {
+                self.sum[step][var] += weight * prob as f64;
+                self.sum_squares[step][var] +=
+                    weight * (prob as f64) * (prob as f64);
+                self.min[step][var] = self.min[step][var].min(prob);
+                self.max[step][var] = self.max[step][var].max(prob);
+                let bin = self.bin(prob);
+                self.histogram[step][var][bin] += weight;
+            }
+        }
+    }
+
+    /// The bin of the histogram containing `prob`, the last one is closed.
+    pub fn bin(&self, prob: f32) -> usize {
+        ((prob.clamp(0.0, 1.0) * self.bins as f32) as usize).min(self.bins - 1)
+    }
+
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+
+    /// The sum of weights of the added trajectories.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// The weighted mean trajectory.
+    pub fn mean(&self) -> Trajectory {
+        self.sum.iter()
+            .map(|sums| sums.iter()
+                .map(|sum| (sum / self.weight) as f32)
+                .collect())
+            .collect()
+    }
+
+    /// The weighted (population) standard deviation after every step.
+    pub fn std(&self) -> Trajectory {
+        self.sum.iter().zip(&self.sum_squares)
+            .map(|(sums, sums_squares)| sums.iter().zip(sums_squares)
+                .map(|(sum, sum_squares)| {
+                    let mean = sum / self.weight;
+                    (sum_squares / self.weight - mean * mean).max(0.0).sqrt()
+                        as f32
+                })
+                .collect())
+            .collect()
+    }
+
+    /// The minimal probabilities after every step.
+    pub fn min(&self) -> &Trajectory {
+        &self.min
+    }
+
+    /// The maximal probabilities after every step.
+    pub fn max(&self) -> &Trajectory {
+        &self.max
+    }
+
+    /// The weights in the bins after every step, indexed by steps,
+    /// variables and bins.
+    pub fn histogram(&self) -> &[Vec<Vec<f64>>] {
+        &self.histogram
+    }
+}
+
+
+/*******************************************************************************
+ * Convergence
+ * ===========
+ * The probabilities of a finite network are eventually periodic in the exact
+ * simulation, IBMFA approaches a fixed point or a cycle. A simulation run
+ * until convergence doubles its horizon until the probabilities repeat,
+ * i.e. the last steps form at least two periods of a cycle (of length one
+ * for a fixed point), up to the tolerance. IBMFA extends the trajectory from
+ * the last probabilities, the exact simulation starts over.
+ ******************************************************************************/
+
+/// The limits of simulations running until convergence.
+#[derive(Clone, Copy, Debug)]
+pub struct ConvergenceLimits {
+    /// The maximal number of steps.
+    pub max_steps: usize,
+    /// The maximal difference of equal probabilities.
+    pub tolerance: f32,
+    /// The maximal length of detected cycles.
+    pub max_period: usize,
+}
+
+/// The probabilities repeat with `period` from `step` on, the limit cycle
+/// (or fixed point for the period 1) starts at `step`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Convergence {
+    pub step: usize,
+    pub period: usize,
+}
+
+impl ConvergenceLimits {
+    /// Finds the shortest period the end of `history` repeats with, and
+    /// the earliest step it repeats from.
+    ///
+    /// * `history` - The initial probabilities followed by the ones after
+    ///     every step.
+    /// * `start` - The first step of the cycle, e.g. the end of a pulse.
+    pub fn convergence(&self, history: &[Vec<f32>], start: usize)
+    -> Option<Convergence> {
+        let equal = |a: &Vec<f32>, b: &Vec<f32>| a.iter().zip(b)
+            .all(|(a, b)| (a - b).abs() <= self.tolerance);
+        (1..=self.max_period)
+            .filter(|period| start + 2 * period <= history.len())
+            .find_map(|period| {
+                let mut step = history.len() - period;
+                while step > start && equal(
+                        &history[step - 1], &history[step - 1 + period]) {
+                    step -= 1;
+                }
+                (history.len() - step >= 2 * period)
+                    .then_some(Convergence { step, period })
+            })
+    }
+}
@@ -28,7 +28,11 @@ impl SymbSyncGraph {
         result
     }
 
-    fn predecessors(&self, initial: &GraphColoredVertices)
+    /// Returns all vertices (backward) reaching `initial`.
+    ///
+    /// Because the synchronous semantics is deterministic in every color,
+    /// this is the basin of `initial` if it is an attractor.
+    pub fn predecessors(&self, initial: &GraphColoredVertices)
     -> GraphColoredVertices {
         let mut new = initial.clone();
         let mut result = initial.clone();
@@ -1,5 +1,11 @@
-use biodivine_lib_param_bn::{VariableId, symbolic_async_graph::
-    {GraphColoredVertices, GraphColors}};
+use biodivine_lib_bdd::{Bdd, BddValuation, BddVariable,
+    boolean_expression::BooleanExpression};
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, VariableId,
+    symbolic_async_graph::{GraphColoredVertices, GraphColors}};
+use rand::Rng;
+
+use crate::driver_set::fixes::DriverSet;
+use crate::utils::function_to_dnf;
 
 use super::SymbSyncGraph;
 
@@ -26,6 +32,31 @@ impl SymbSyncGraph {
         GraphColoredVertices::new(output, &self.context)
     }
 
+    /// Returns the graph with vertices of `driver_set` fixed permanently.
+    ///
+    /// Only the synchronous transition relation is affected, the parametrized
+    /// update functions (used by IBMFA) remain the same.
+    pub fn fixed_by(&self, driver_set: &DriverSet) -> SymbSyncGraph {
+        let mut fixed = self.clone();
+        for (&var_id, &value) in driver_set {
+            let next_var = self.context.get_extra_state_variable(var_id, 0);
+            // Every conjunct of the relation contains its own next variable
+            // only, so the projection just forgets the update of `var_id`.
+            fixed.total_update_function = fixed.total_update_function
+                .var_project(next_var)
+                .var_select(next_var, value);
+        }
+        fixed
+    }
+
+    /// Returns the graph with the valid parametrizations restricted
+    /// to `colors`, e.g. by fixes of parameters.
+    pub fn restricted(&self, colors: &GraphColors) -> SymbSyncGraph {
+        let mut restricted = self.clone();
+        restricted.unit_bdd = self.unit_bdd.and(colors.as_bdd());
+        restricted
+    }
+
     /// Returns all vertices and valid parametrizations
     pub fn unit_colored_vertices(&self) -> GraphColoredVertices {
         GraphColoredVertices::new(self.unit_bdd.clone(), &self.context)
@@ -50,4 +81,178 @@ impl SymbSyncGraph {
         GraphColoredVertices::new(
             self.unit_bdd.var_select(bdd_var, value), &self.context)
     }
+
+    /// Returns valid parametrizations in which the update function of
+    /// `variable` is exactly `function` (a function of state variables).
+    pub fn function_colors(&self, variable: VariableId, function: &Bdd)
+    -> Bdd {
+        let pupdate_function = &self.pupdate_functions[variable.to_index()];
+        // The functions differ in no state
+        let differ = pupdate_function.get_function()
+            .xor(function)
+            .project(self.context.state_variables());
+        self.unit_bdd.and_not(&differ)
+    }
+
+    /// Returns valid parametrizations in which `regulator` is essential
+    /// in the update function of `target`.
+    pub fn essential_colors(&self, regulator: VariableId, target: VariableId)
+    -> Bdd {
+        let regulator = self.context.get_state_variable(regulator);
+        let function = self.pupdate_functions[target.to_index()].get_function();
+        function.var_restrict(regulator, true)
+            .xor(&function.var_restrict(regulator, false))
+            .project(self.context.state_variables())
+    }
+
+    /// Returns the color of `network`, a fully specified instance
+    /// of the model.
+    ///
+    /// The variables are matched by names, their update functions must not
+    /// contain any parameters.
+    pub fn instance_colors(&self, network: &BooleanNetwork)
+    -> Result<GraphColors, String> {
+        let bdd_var_set = self.context.bdd_variable_set();
+        let mut colors = self.unit_bdd.clone();
+        for var_id in self.bn.variables() {
+            let name = self.bn.get_variable_name(var_id);
+            let instance_var = network.as_graph().find_variable(name)
+                .ok_or(format!("The network does not contain variable \
+                    '{name}'."))?;
+            let fn_update = network.get_update_function(instance_var)
+                .as_ref()
+                .filter(|fn_update| fn_update.collect_parameters().is_empty())
+                .ok_or(format!("The update function of '{name}' is not \
+                    fully specified."))?;
+            let function = BooleanExpression::try_from(
+                    fn_update.to_string(network).as_str())
+                .ok()
+                .and_then(|expression|
+                    bdd_var_set.safe_eval_expression(&expression))
+                .ok_or(format!("The update function of '{name}' refers \
+                    to a variable outside the model."))?;
+            colors = colors.and(&self.function_colors(var_id, &function));
+        }
+        if colors.is_false() {
+            return Err("The network is not an instance of the model.".into());
+        }
+        Ok(GraphColors::new(colors, &self.context))
+    }
+
+    /// Returns the admissible update functions of `variable` in `colors`,
+    /// each with the colors using it.
+    pub fn function_classes(&self, variable: VariableId, colors: &GraphColors)
+    -> Vec<(Bdd, GraphColors)> {
+        let pupdate_function = &self.pupdate_functions[variable.to_index()];
+        let mut classes: Vec<(Bdd, GraphColors)> = Vec::new();
+        for function in pupdate_function.explicit_in(colors.as_bdd(), self) {
+            // Different parametrizations may give the same function
+            if classes.iter().any(|(other, _)| *other == function) {
+                continue;
+            }
+            let function_colors = colors.as_bdd()
+                .and(&self.function_colors(variable, &function));
+            if !function_colors.is_false() {
+                classes.push((function,
+                    GraphColors::new(function_colors, &self.context)));
+            }
+        }
+        classes
+    }
+
+    /// Returns each color of `colors` once, with all state variables set
+    /// to false.
+    fn canonical_colors(&self, colors: &GraphColors) -> Bdd {
+        colors.as_bdd()
+            .and(&self.all_false_bdd
+                .project(self.context.parameter_variables()))
+    }
+
+    /// Returns the color with `index` in `colors`.
+    ///
+    /// The colors are ordered lexicographically by the values of parameter
+    /// variables, so the index of a color is stable for the same model.
+    pub fn nth_color(&self, colors: &GraphColors, mut index: usize)
+    -> Option<BddValuation> {
+        let mut color = self.canonical_colors(colors);
+        if color.exact_cardinality() <= index.into() {
+            return None;
+        }
+        for &bdd_var in self.context.parameter_variables() {
+            let low = color.var_select(bdd_var, false);
+            let num_low = low.exact_cardinality();
+            if num_low > index.into() {
+                color = low;
+            } else {
+                index -= usize::try_from(&num_low).ok()?;
+                color = color.var_select(bdd_var, true);
+            }
+        }
+        color.sat_witness()
+    }
+
+    /// Returns the colors of `colors` in the order of their indices,
+    /// see `nth_color`.
+    pub fn iter_colors<'a>(&'a self, colors: &GraphColors)
+    -> impl Iterator<Item = BddValuation> + 'a {
+        let parameter_vars = self.context.parameter_variables();
+        // Depth-first, the low branch first
+        let mut stack = vec![(self.canonical_colors(colors), 0)];
+        std::iter::from_fn(move || {
+            while let Some((color, depth)) = stack.pop() {
+                if color.is_false() {
+                    continue;
+                }
+                let Some(&bdd_var) = parameter_vars.get(depth) else {
+                    return color.sat_witness();
+                };
+                stack.push((color.var_select(bdd_var, true), depth + 1));
+                stack.push((color.var_select(bdd_var, false), depth + 1));
+            }
+            None
+        })
+    }
+
+    /// Returns a color of `colors` picked uniformly at random.
+    pub fn random_color<R: Rng>(&self, colors: &GraphColors, rng: &mut R)
+    -> Option<BddValuation> {
+        let mut color = self.canonical_colors(colors);
+        for &bdd_var in self.context.parameter_variables() {
+            let low = color.var_select(bdd_var, false);
+            let value = rng.gen::<f64>() * color.cardinality()
+                >= low.cardinality();
+            color = if value { color.var_select(bdd_var, true) } else { low };
+        }
+        color.sat_witness()
+    }
+
+    /// Returns the fully specified instance of the model given by `color`,
+    /// the inverse of `instance_colors`.
+    ///
+    /// Every update function is written in an irredundant disjunctive
+    /// normal form.
+    pub fn instantiate(&self, color: &BddValuation) -> BooleanNetwork {
+        let mut network = BooleanNetwork::new(self.bn.as_graph().clone());
+        let state_var = |bdd_var: BddVariable| VariableId::from_index(
+            self.context.state_variables().iter()
+                .position(|state_var| *state_var == bdd_var)
+                .unwrap());
+        for (var_id, pupdate_function) in
+                self.bn.variables().zip(&self.pupdate_functions) {
+            let function = pupdate_function.restricted(color);
+            let fn_update = function_to_dnf(&function, &self.context)
+                .into_iter()
+                .map(|clause| clause.to_values().into_iter()
+                    .map(|(bdd_var, value)| {
+                        let literal = FnUpdate::Var(state_var(bdd_var));
+                        if value { literal } else { literal.negation() }
+                    })
+                    .reduce(FnUpdate::and)
+                    .unwrap_or(FnUpdate::Const(true)))
+                .reduce(FnUpdate::or)
+                .unwrap_or(FnUpdate::Const(false));
+            network.add_update_function(var_id, fn_update).unwrap();
+        }
+        network
+    }
 }
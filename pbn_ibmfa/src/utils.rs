@@ -23,13 +23,102 @@ pub fn add_self_regulations(mut model: BooleanNetwork) -> BooleanNetwork {
     model
 }
 
-/** A number of functions used for printing follows. They convert a type
+/// Returns an irredundant disjunctive normal form of `function`, a list of
+/// prime implicants.
+///
+/// The paths of the bdd are extended to prime implicants by dropping
+/// literals, then the implicants covered by the others are removed.
+pub fn function_to_dnf(function: &Bdd, context: &SymbolicContext)
+-> Vec<BddPartialValuation> {
+    let bdd_var_set = context.bdd_variable_set();
+    let implies_function = |clause: &BddPartialValuation|
+        bdd_var_set.mk_conjunctive_clause(clause).imp(function).is_true();
+
+    let mut implicants: Vec<BddPartialValuation> = Vec::new();
+    for mut clause in function.sat_clauses() {
+        for (bdd_var, value) in clause.to_values() {
+            clause.unset_value(bdd_var);
+            if !implies_function(&clause) {
+                clause.set_value(bdd_var, value);
+            }
+        }
+        if !implicants.contains(&clause) {
+            implicants.push(clause);
+        }
+    }
+
+    // The shortest implicants first
+    implicants.sort_by_key(|clause| clause.to_values().len());
+    let mut i = implicants.len();
+    while i > 0 {
+        i -= 1;
+        let others = implicants.iter().enumerate()
+            .filter(|(j, _)| *j != i)
+            .fold(bdd_var_set.mk_false(), |acc, (_, clause)|
+                acc.or(&bdd_var_set.mk_conjunctive_clause(clause)));
+        if bdd_var_set.mk_conjunctive_clause(&implicants[i])
+                .imp(&others).is_true() {
+            implicants.remove(i);
+        }
+    }
+    implicants
+}
+
+/* A number of functions used for printing follows. They convert a type
  * from the biodivine-lib-bdd or biodivine-lib-param-bn to `String`. */
 
 pub fn bdd_to_str(bdd: &Bdd, context: &SymbolicContext) -> String {
     format!("{}", bdd.to_boolean_expression(context.bdd_variable_set()))
 }
 
+/// Prints a function of state variables in a readable form. Conjunctions
+/// and disjunctions of literals are printed as `AND(..)` and `OR(..)` gates.
+pub fn function_to_str(function: &Bdd, context: &SymbolicContext) -> String {
+    let bdd_var_set = context.bdd_variable_set();
+    let literals = |clause: &BddPartialValuation, positive: bool|
+        clause.to_values().iter()
+            .map(|&(bdd_var, value)| format!("{}{}",
+                if value == positive { "" } else { "!" },
+                bdd_var_set.name_of(bdd_var)))
+            .collect::<Vec<_>>()
+            .join(", ");
+    let single_clause = |bdd: &Bdd| {
+        let clauses = bdd.sat_clauses().take(2).collect::<Vec<_>>();
+        match clauses.as_slice() {
+            [clause] if clause.to_values().len() > 1 => Some(clause.clone()),
+            _ => None,
+        }
+    };
+
+    if let Some(clause) = single_clause(function) {
+        format!("AND({})", literals(&clause, true))
+    } else if let Some(clause) = single_clause(&function.not()) {
+        format!("OR({})", literals(&clause, false))
+    } else {
+        bdd_to_str(function, context)
+    }
+}
+
+/// Prints clauses of `function_to_dnf` as `a & !b | c`.
+pub fn dnf_to_str(clauses: &[BddPartialValuation], context: &SymbolicContext)
+-> String {
+    if clauses.is_empty() {
+        return String::from("false");
+    }
+    let bdd_var_set = context.bdd_variable_set();
+    clauses.iter()
+        .map(|clause| match clause.to_values().as_slice() {
+            [] => String::from("true"),
+            literals => literals.iter()
+                .map(|&(bdd_var, value)| format!("{}{}",
+                    if value { "" } else { "!" }, bdd_var_set.name_of(bdd_var)))
+                .collect::<Vec<_>>()
+                .join(" & "),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
 pub fn bdd_var_to_str(bdd_var: BddVariable, context: &SymbolicContext)
 -> String {
     format!("{}({bdd_var})", context.bdd_variable_set().name_of(bdd_var))
@@ -75,32 +164,35 @@ pub fn vertices_to_str(vertices: &GraphVertices, context: &SymbolicContext)
         .collect::<String>())
 }
 
-/// Prints the parametrized update functions for `sync_graph`.
+/// States of `vertices` as strings of values in the order of state variables.
+pub fn vertices_to_bitstrings(
+    vertices: &GraphVertices,
+    context: &SymbolicContext,
+) -> Vec<String> {
+    let all_false: Bdd = BddValuation::all_false(
+        context.bdd_variable_set().num_vars()).into();
+    all_false
+        .project(context.state_variables())
+        .and(vertices.as_bdd())
+        .sat_valuations()
+        .map(|valuation| context.state_variables().iter()
+            .map(|bdd_var| if valuation[*bdd_var] { '1' } else { '0' })
+            .collect())
+        .collect()
+}
+
+/// Prints the admissible update functions of each variable with the number
+/// of colors using them.
 pub fn print_update_functions(sync_graph: &SymbSyncGraph) {
-    for pupdate_function in sync_graph.get_pupdate_functions() {
-        let parametrizations = pupdate_function.get_parametrizations();
-        println!("{}", 
-            bdd_to_str(&parametrizations, sync_graph.symbolic_context()));
-
-        let pars = sync_graph.get_all_false()
-            .project(pupdate_function.get_parameters())
-            .and(parametrizations);
-
-        for parametrization in pars.sat_valuations() {
-            println!("Parametrization: {}", valuation_to_str(
-                    &parametrization,
-                    pupdate_function.get_parameters().iter().copied(),
-                    sync_graph.symbolic_context()));
-            let f = pupdate_function.restricted(&parametrization);
-            println!("Update function: {}",
-                bdd_to_str(&f, sync_graph.symbolic_context()));
-
-            for valuation in f.sat_clauses() {
-                println!("\t{}", partial_valuation_to_str(
-                        &valuation, sync_graph.symbolic_context()));
-            }
+    let context = sync_graph.symbolic_context();
+    for var_id in sync_graph.as_network().variables() {
+        println!("{}:", sync_graph.as_network().get_variable_name(var_id));
+        for (function, colors) in sync_graph
+                .function_classes(var_id, &sync_graph.unit_colors()) {
+            println!("\t{} ({} colors)",
+                dnf_to_str(&function_to_dnf(&function, context), context),
+                colors.approx_cardinality());
         }
-        println!();
     }
 }
 
@@ -134,3 +226,31 @@ fn variations_with_replacement_r<T: Default + Clone>(
         variations_with_replacement_r(values, current, index + 1, result);
     }
 }
+
+/// Returns a `Vec` of combinations without replacement
+///
+/// * `values` - Values to choose from, keeping their order.
+/// * `num` - The number of values to build one combination.
+pub fn combinations<T: Clone>(values: &[T], num: usize) -> Vec<Vec<T>> {
+    let mut result = Vec::new();
+    combinations_r(values, &mut Vec::with_capacity(num), num, &mut result);
+    result
+}
+
+fn combinations_r<T: Clone>(
+    values: &[T],
+    current: &mut Vec<T>,
+    num: usize,
+    result: &mut Vec<Vec<T>>
+) {
+    if current.len() == num {
+        result.push(current.clone());
+        return;
+    }
+
+    for (i, value) in values.iter().enumerate() {
+        current.push(value.clone());
+        combinations_r(&values[i + 1..], current, num, result);
+        current.pop();
+    }
+}
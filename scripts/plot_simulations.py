@@ -4,9 +4,21 @@ from matplotlib import pyplot as plt
 data = json.load(open(sys.argv[1]))
 variables = data['state_variables']
 values = data['simulation']
+# Given by brute-force simulations, the spread across colors
+envelope = data.get('envelope')
+std = data.get('std')
 
 for var in variables:
-    plt.plot(range(len(values[var])), values[var], label=var)
+    steps = range(len(values[var]))
+    line, = plt.plot(steps, values[var], label=var)
+    if envelope is not None:
+        plt.fill_between(steps, envelope['min'][var], envelope['max'][var],
+                         color=line.get_color(), alpha=0.1)
+    if std is not None:
+        plt.fill_between(steps,
+                         [m - s for m, s in zip(values[var], std[var])],
+                         [m + s for m, s in zip(values[var], std[var])],
+                         color=line.get_color(), alpha=0.2)
 
 gca = plt.gca()
 gca.set_ylim([-0.05, 1.05])